@@ -1,17 +1,164 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
+use crate::{multilingual, output, sampling};
+
 #[derive(Parser)]
 #[command(name = "cc-langid-sampler")]
 #[command(author = "Pedro Ortiz Suarez <pedro@commoncrawl.org>")]
 #[command(version = "0.1.0")]
-#[command(about = "Compute host and domain db", long_about = None)]
-pub struct Args {
-    /// Folder containing the indices
+#[command(
+    about = "Sample and query the language folders of a Common Crawl language identification corpus",
+    long_about = None
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Sample documents from each language folder
+    Sample(SampleArgs),
+    /// Run a SQL query over the language folders
+    Query(QueryArgs),
+}
+
+/// Options of the DataFusion session
+#[derive(clap::Args)]
+pub struct SessionArgs {
+    /// Number of partitions DataFusion splits each query in
+    #[arg(long, default_value_t = std::thread::available_parallelism().map_or(1, |n| n.get()))]
+    pub target_partitions: usize,
+
+    /// Memory shared by the queries running at the same time, in bytes. Sorts spill to disk
+    /// when it runs out. Unbounded when not set
+    #[arg(long, value_name = "BYTES")]
+    pub memory_limit: Option<usize>,
+}
+
+#[derive(clap::Args)]
+pub struct QueryArgs {
+    /// Folder containing the language folders. Each one is registered as a table named after
+    /// the folder, and all of them as a `corpus` table with a `lang` column. Rows have a
+    /// `source_file` column with the path of their file, unless the files already have one
+    #[arg(value_name = "INPUT FOLDER")]
+    pub src: PathBuf,
+
+    /// SQL query, e.g. `SELECT lang, count(*) FROM corpus GROUP BY lang`
+    #[arg(value_name = "SQL")]
+    pub sql: String,
+
+    /// File to write the results to. They are printed as a table when not set
+    #[arg(long, value_name = "OUTPUT FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Format of the output file. Defaults to the format matching its extension
+    #[arg(long, requires = "output")]
+    pub format: Option<output::OutputFormat>,
+
+    #[command(flatten)]
+    pub session: SessionArgs,
+}
+
+#[derive(clap::Args)]
+pub struct SampleArgs {
+    /// Folder containing the language folders
     #[arg(value_name = "INPUT FOLDER")]
     pub src: PathBuf,
 
-    /// Parquet file to write
-    #[arg(value_name = "DESTINATION FILE")]
-    pub dst: PathBuf,
+    /// Folder to write the samples and the manifest to. Not needed with `--dry-run`
+    #[arg(value_name = "DESTINATION FOLDER", required_unless_present = "dry_run")]
+    pub dst: Option<PathBuf>,
+
+    /// Count the documents of each language, and the ones passing the filters, instead of
+    /// sampling them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Number of documents sampled per language
+    #[arg(long, default_value_t = sampling::SAMPLE_SIZE, conflicts_with = "prob_bins")]
+    pub sample_size: usize,
+
+    /// Comma separated lang folders to sample, e.g. `fr,hi,th`. All of them when not set
+    #[arg(long, value_name = "LANGUAGES", value_delimiter = ',')]
+    pub languages: Vec<String>,
+
+    /// Comma separated lang folders not to sample
+    #[arg(long, value_name = "LANGUAGES", value_delimiter = ',')]
+    pub exclude_languages: Vec<String>,
+
+    /// Format of the sample files
+    #[arg(long, value_enum, default_value_t = output::OutputFormat::Parquet)]
+    pub format: output::OutputFormat,
+
+    /// Seed of the row sampling. The same seed on the same files gives the same sample.
+    /// A random seed is picked and printed when none is given
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// TOML file with the filter policy: category and quality warning rules, thresholds,
+    /// SQL predicates and per-language overrides. Without it, documents with quality warnings,
+    /// without a UT1 category or with a dangerous one are dropped
+    #[arg(long, value_name = "POLICY FILE")]
+    pub policy: Option<PathBuf>,
+
+    /// Number of languages sampled at the same time
+    #[arg(long, default_value_t = 4)]
+    pub concurrency: usize,
+
+    /// Write every language to one `sample.parquet` file instead of one `{lang}.parquet` per
+    /// language. Rows carry their language in the `sample_lang` column
+    #[arg(long)]
+    pub combined: bool,
+
+    /// Stratify each language by `identified_doc_prob`: `deciles`, or comma separated bin
+    /// edges like `0,0.5,0.8,0.95,1`. The rows of each bin are picked uniformly
+    #[arg(long, value_name = "BINS", value_parser = sampling::parse_bins)]
+    pub prob_bins: Option<sampling::BinEdges>,
+
+    /// Sample first the documents whose language signals disagree: OSCAR's document language,
+    /// CLD2's language and the most common line language. The normalized labels and their
+    /// agreement are written to the `doc_lang`, `cld2_lang`, `line_lang` and
+    /// `language_agreement` columns
+    #[arg(long, conflicts_with = "prob_bins")]
+    pub disagreements: bool,
+
+    /// Number of documents sampled per bin with `--prob-bins`
+    #[arg(long, default_value_t = 100, requires = "prob_bins")]
+    pub per_bin: usize,
+
+    /// Only sample multilingual and code-switched documents, selected with their per-line
+    /// languages. Their mixture is written to a `line_languages` column
+    #[arg(long)]
+    pub multilingual: bool,
+
+    /// Number of line languages a document needs with `--multilingual`
+    #[arg(long, default_value_t = 2, requires = "multilingual")]
+    pub min_line_languages: usize,
+
+    /// Share of the lines of a document a language needs to count as one of its languages
+    #[arg(long, default_value_t = 0.1, value_name = "SHARE", value_parser = multilingual::parse_share::<f64>, requires = "multilingual")]
+    pub min_language_share: f64,
+
+    /// Lines whose language was identified with a lower probability are ignored
+    #[arg(long, default_value_t = 0.0, value_name = "PROB", value_parser = multilingual::parse_share::<f32>, requires = "multilingual")]
+    pub min_line_prob: f32,
+
+    /// Languages a document must contain with `--multilingual`, like `eng+hin`
+    #[arg(long, value_name = "LANGUAGES", value_parser = multilingual::parse_languages, requires = "multilingual")]
+    pub language_pair: Option<multilingual::Languages>,
+
+    /// File listing domains and URL prefixes to never sample, one per line. Domains also
+    /// deny their subdomains, entries with a scheme like `https://example.com/forum/` are URL
+    /// prefixes. Can be given several times
+    #[arg(long, value_name = "DENY LIST")]
+    pub deny_list: Vec<PathBuf>,
+
+    /// Maximum number of documents per host in each language sample
+    #[arg(long)]
+    pub max_per_host: Option<usize>,
+
+    #[command(flatten)]
+    pub session: SessionArgs,
 }
@@ -0,0 +1,252 @@
+use datafusion::arrow::array::{ArrayRef, AsArray, BooleanArray, StringArray};
+use datafusion::arrow::datatypes::DataType;
+use datafusion::error::Result;
+use datafusion::logical_expr::window_function::row_number;
+use datafusion::logical_expr::{ColumnarValue, Volatility};
+use datafusion::prelude::*;
+use serde::Serialize;
+use std::{collections::HashSet, fs, path::PathBuf, sync::Arc};
+use url::Url;
+
+use crate::sampling::sample_order;
+
+/// Lowercased host of a document URL, without a trailing dot
+fn url_host(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    Some(url.host_str()?.trim_end_matches('.').to_lowercase())
+}
+
+/// Domains and URL prefixes whose documents are never sampled, read from local lists
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DenyList {
+    /// Files the list was read from
+    pub files: Vec<PathBuf>,
+    /// Denied domains. A domain also denies all its subdomains
+    #[serde(serialize_with = "serialize_len")]
+    pub domains: HashSet<String>,
+    /// Denied URL prefixes, like `https://example.com/forum/`
+    #[serde(serialize_with = "serialize_len")]
+    pub url_prefixes: Vec<String>,
+}
+
+fn serialize_len<T, S>(values: T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: IntoIterator,
+    T::IntoIter: ExactSizeIterator,
+    S: serde::Serializer,
+{
+    serializer.serialize_u64(values.into_iter().len() as u64)
+}
+
+impl DenyList {
+    /// Reads deny lists with one domain or URL prefix per line. Entries with a scheme, like
+    /// `https://example.com/forum/`, are URL prefixes, other ones are domains. Empty lines and
+    /// lines starting with `#` are ignored.
+    pub fn load(files: &[PathBuf]) -> Result<Self> {
+        let mut deny_list = DenyList {
+            files: files.to_vec(),
+            ..Default::default()
+        };
+        for file in files {
+            for entry in fs::read_to_string(file)?.lines() {
+                let entry = entry.trim();
+                if entry.is_empty() || entry.starts_with('#') {
+                    continue;
+                }
+                if entry.contains("://") {
+                    deny_list.url_prefixes.push(entry.to_string());
+                } else {
+                    let domain = entry.trim_start_matches("*.").trim_end_matches('.');
+                    deny_list.domains.insert(domain.to_lowercase());
+                }
+            }
+        }
+        Ok(deny_list)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty() && self.url_prefixes.is_empty()
+    }
+
+    /// Whether a URL has a denied prefix, or its host is a denied domain or one of its
+    /// subdomains
+    fn denies(&self, url: &str) -> bool {
+        if self
+            .url_prefixes
+            .iter()
+            .any(|prefix| url.starts_with(prefix))
+        {
+            return true;
+        }
+        let Some(host) = url_host(url) else {
+            return false;
+        };
+        let mut suffix = host.as_str();
+        loop {
+            if self.domains.contains(suffix) {
+                return true;
+            }
+            match suffix.split_once('.') {
+                Some((_, parent)) => suffix = parent,
+                None => return false,
+            }
+        }
+    }
+}
+
+/// Rules on the hosts of the documents of a language sample
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HostFilter {
+    pub deny_list: DenyList,
+    /// Maximum number of documents per host in each language sample
+    pub max_per_host: Option<usize>,
+}
+
+impl HostFilter {
+    pub fn is_set(&self) -> bool {
+        !self.deny_list.is_empty() || self.max_per_host.is_some()
+    }
+
+    /// Drops the documents of denied URLs, and keeps at most `max_per_host` documents per
+    /// `warc_target_uri` host, picked uniformly with the sample key. Applied before sampling,
+    /// so that capped hosts leave room for documents of other hosts.
+    pub fn apply(&self, df: DataFrame, seed: u64) -> Result<DataFrame> {
+        let mut df = df;
+        if !self.deny_list.is_empty() {
+            let deny_list = self.deny_list.clone();
+            let is_denied = create_udf(
+                "is_denied",
+                vec![DataType::Utf8],
+                Arc::new(DataType::Boolean),
+                Volatility::Immutable,
+                Arc::new(move |args: &[ColumnarValue]| {
+                    let args = ColumnarValue::values_to_arrays(args)?;
+                    let denied: BooleanArray = args[0]
+                        .as_string::<i32>()
+                        .iter()
+                        .map(|url| Some(url.is_some_and(|url| deny_list.denies(url))))
+                        .collect();
+                    Ok(ColumnarValue::Array(Arc::new(denied) as ArrayRef))
+                }),
+            );
+            df = df.filter(is_denied.call(vec![col("warc_target_uri")]).not())?;
+        }
+
+        if let Some(max_per_host) = self.max_per_host {
+            let host = create_udf(
+                "url_host",
+                vec![DataType::Utf8],
+                Arc::new(DataType::Utf8),
+                Volatility::Immutable,
+                Arc::new(|args: &[ColumnarValue]| {
+                    let args = ColumnarValue::values_to_arrays(args)?;
+                    let hosts: StringArray = args[0]
+                        .as_string::<i32>()
+                        .iter()
+                        .map(|url| url.and_then(url_host))
+                        .collect();
+                    Ok(ColumnarValue::Array(Arc::new(hosts) as ArrayRef))
+                }),
+            );
+            let columns: Vec<Expr> = df
+                .schema()
+                .columns()
+                .into_iter()
+                .map(Expr::Column)
+                .collect();
+            let host_rank = row_number()
+                .partition_by(vec![col("host")])
+                .order_by(sample_order(seed))
+                .build()?
+                .alias("host_rank");
+            // documents without a parseable URL are not capped
+            df = df
+                .with_column("host", host.call(vec![col("warc_target_uri")]))?
+                .window(vec![host_rank])?
+                .filter(
+                    col("host")
+                        .is_null()
+                        .or(col("host_rank").lt_eq(lit(max_per_host as u64))),
+                )?
+                .select(columns)?;
+        }
+        Ok(df)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{corpus, doc, ids, temp_dir, Doc};
+
+    /// The deny list of a test, written to a folder of its own
+    fn deny_list(test: &str) -> DenyList {
+        let dir = temp_dir(test);
+        let path = dir.join("deny-list.txt");
+        fs::write(
+            &path,
+            "# spam\n*.Spam.example.\n\nhttps://forum.example.org/private/\n",
+        )
+        .unwrap();
+        let deny_list = DenyList::load(std::slice::from_ref(&path)).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+        deny_list
+    }
+
+    #[test]
+    fn lists_hold_domains_and_url_prefixes() {
+        let deny_list = deny_list("deny-list-entries");
+        assert_eq!(
+            deny_list.domains,
+            HashSet::from(["spam.example".to_string()])
+        );
+        assert_eq!(
+            deny_list.url_prefixes,
+            vec!["https://forum.example.org/private/"]
+        );
+        assert!(DenyList::default().is_empty());
+    }
+
+    #[test]
+    fn domains_deny_their_subdomains() {
+        let deny_list = deny_list("deny-list-subdomains");
+        assert!(deny_list.denies("https://spam.example/page"));
+        assert!(deny_list.denies("http://www.SPAM.example./page"));
+        assert!(deny_list.denies("https://forum.example.org/private/thread/1"));
+
+        assert!(!deny_list.denies("https://notspam.example/page"));
+        assert!(!deny_list.denies("https://example/page"));
+        assert!(!deny_list.denies("https://forum.example.org/public/thread/1"));
+        assert!(!deny_list.denies("not a url"));
+    }
+
+    #[tokio::test]
+    async fn hosts_are_denied_and_capped() {
+        let at = |id: &str, url: Option<&str>| Doc {
+            url: url.map(str::to_string),
+            ..doc(id)
+        };
+        let docs = vec![
+            at("spam", Some("https://www.spam.example/")),
+            at("a1", Some("https://a.example/1")),
+            at("a2", Some("https://A.example/2")),
+            at("a3", Some("https://a.example/3")),
+            at("b1", Some("https://b.example/1")),
+            at("broken1", Some("not a url")),
+            at("broken2", None),
+        ];
+        let filter = HostFilter {
+            deny_list: deny_list("deny-list-hosts"),
+            max_per_host: Some(2),
+        };
+        let df = filter.apply(corpus(&docs), 1).unwrap();
+        let columns = df.schema().fields().len();
+        let mut kept = ids(df).await;
+        kept.sort();
+
+        let capped: Vec<_> = kept.iter().filter(|id| id.starts_with('a')).collect();
+        assert_eq!(capped.len(), 2);
+        assert_eq!(kept[2..], ["b1", "broken1", "broken2"]);
+        assert_eq!(columns, corpus(&docs).schema().fields().len());
+    }
+}
@@ -0,0 +1,178 @@
+//! In-memory documents with the columns of the OSCAR parquet files, for the unit tests
+
+use datafusion::arrow::array::{
+    ArrayRef, AsArray, Float32Array, Float32Builder, ListArray, ListBuilder, RecordBatch,
+    StringArray, StringBuilder,
+};
+use datafusion::dataframe::DataFrameWriteOptions;
+use datafusion::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A document of the corpus, only holding the columns the samplers look at
+#[derive(Debug, Clone, Default)]
+pub struct Doc {
+    pub id: String,
+    pub url: Option<String>,
+    pub lang: String,
+    pub prob: f32,
+    pub cld2_langs: Option<Vec<&'static str>>,
+    pub line_langs: Option<Vec<&'static str>>,
+    pub line_probs: Option<Vec<f32>>,
+    pub harmful_pp: Option<f32>,
+    pub quality_warnings: Option<Vec<&'static str>>,
+    pub categories: Option<Vec<&'static str>>,
+}
+
+/// An English document identified with a high probability and no other signal
+pub fn doc(id: &str) -> Doc {
+    Doc {
+        id: id.to_string(),
+        lang: "en".to_string(),
+        prob: 0.9,
+        ..Default::default()
+    }
+}
+
+/// A list column of strings, null where a row has no list
+pub fn string_lists<'a>(rows: impl IntoIterator<Item = Option<&'a [&'a str]>>) -> ListArray {
+    let mut lists = ListBuilder::new(StringBuilder::new());
+    for row in rows {
+        match row {
+            Some(values) => {
+                values
+                    .iter()
+                    .for_each(|value| lists.values().append_value(value));
+                lists.append(true);
+            }
+            None => lists.append(false),
+        }
+    }
+    lists.finish()
+}
+
+/// A list column of floats, null where a row has no list
+pub fn float_lists<'a>(rows: impl IntoIterator<Item = Option<&'a [f32]>>) -> ListArray {
+    let mut lists = ListBuilder::new(Float32Builder::new());
+    for row in rows {
+        match row {
+            Some(values) => {
+                values
+                    .iter()
+                    .for_each(|value| lists.values().append_value(*value));
+                lists.append(true);
+            }
+            None => lists.append(false),
+        }
+    }
+    lists.finish()
+}
+
+/// A DataFrame holding the documents, in order
+pub fn corpus(docs: &[Doc]) -> DataFrame {
+    let strings = |values: Vec<Option<String>>| Arc::new(StringArray::from(values)) as ArrayRef;
+    let batch = RecordBatch::try_from_iter(vec![
+        (
+            "warc_record_id",
+            strings(docs.iter().map(|d| Some(d.id.clone())).collect()),
+        ),
+        (
+            "warc_target_uri",
+            strings(docs.iter().map(|d| d.url.clone()).collect()),
+        ),
+        (
+            "content",
+            strings(
+                docs.iter()
+                    .map(|d| Some(format!("Text of {}", d.id)))
+                    .collect(),
+            ),
+        ),
+        (
+            "identified_doc_lang",
+            strings(docs.iter().map(|d| Some(d.lang.clone())).collect()),
+        ),
+        (
+            "identified_doc_prob",
+            Arc::new(Float32Array::from_iter_values(docs.iter().map(|d| d.prob))),
+        ),
+        (
+            "warc_identified_content_language",
+            Arc::new(string_lists(docs.iter().map(|d| d.cld2_langs.as_deref()))),
+        ),
+        (
+            "sentence_langs",
+            Arc::new(string_lists(docs.iter().map(|d| d.line_langs.as_deref()))),
+        ),
+        (
+            "sentences_probs",
+            Arc::new(float_lists(docs.iter().map(|d| d.line_probs.as_deref()))),
+        ),
+        (
+            "harmful_pp",
+            Arc::new(Float32Array::from_iter(docs.iter().map(|d| d.harmful_pp))),
+        ),
+        (
+            "quality_warnings",
+            Arc::new(string_lists(
+                docs.iter().map(|d| d.quality_warnings.as_deref()),
+            )),
+        ),
+        (
+            "categories",
+            Arc::new(string_lists(docs.iter().map(|d| d.categories.as_deref()))),
+        ),
+    ])
+    .unwrap();
+    SessionContext::new().read_batch(batch).unwrap()
+}
+
+/// Values of a string column of a DataFrame, in row order
+pub async fn strings(df: DataFrame, column: &str) -> Vec<Option<String>> {
+    let batches = df
+        .select_columns(&[column])
+        .unwrap()
+        .collect()
+        .await
+        .unwrap();
+    batches
+        .iter()
+        .flat_map(|batch| {
+            batch
+                .column(0)
+                .as_string::<i32>()
+                .iter()
+                .map(|value| value.map(str::to_string))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Record ids of the documents of a DataFrame, in row order
+pub async fn ids(df: DataFrame) -> Vec<String> {
+    strings(df, "warc_record_id")
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// An empty folder in the system temporary folder, unique to a test of this process
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("cc-langid-sampler-{}-{}", name, std::process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes a DataFrame to a parquet file, creating its folder
+pub async fn write_parquet(df: DataFrame, path: &Path) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    df.write_parquet(path.to_str().unwrap(), DataFrameWriteOptions::new(), None)
+        .await
+        .unwrap();
+}
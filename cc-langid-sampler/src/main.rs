@@ -1,16 +1,93 @@
 use clap::Parser;
+use datafusion::error::Result;
 
 mod cli;
+mod domains;
+#[cfg(test)]
+mod fixtures;
+mod manifest;
+mod multilingual;
+mod output;
+mod policy;
+mod query;
 mod sampler;
+mod sampling;
+mod signals;
+
+async fn sample(args: cli::SampleArgs) -> Result<()> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    eprintln!("Sampling with seed {}", seed);
+
+    let policy = match &args.policy {
+        Some(path) => policy::FilterPolicy::load(path)?,
+        None => policy::FilterPolicy::default(),
+    };
+
+    let options = sampler::SamplerOptions {
+        seed,
+        languages: args.languages,
+        exclude_languages: args.exclude_languages,
+        policy,
+        sampling: match args.prob_bins {
+            Some(edges) => sampling::Sampling::ProbBins {
+                edges,
+                per_bin: args.per_bin,
+            },
+            None if args.disagreements => sampling::Sampling::Disagreement {
+                size: args.sample_size,
+            },
+            None => sampling::Sampling::Uniform {
+                size: args.sample_size,
+            },
+        },
+        multilingual: args.multilingual.then(|| multilingual::Multilingual {
+            min_languages: args.min_line_languages,
+            min_share: args.min_language_share,
+            min_line_prob: args.min_line_prob,
+            required: args.language_pair.clone().unwrap_or_default(),
+        }),
+        hosts: domains::HostFilter {
+            deny_list: domains::DenyList::load(&args.deny_list)?,
+            max_per_host: args.max_per_host,
+        },
+        concurrency: args.concurrency,
+        target_partitions: args.session.target_partitions,
+        memory_limit: args.session.memory_limit,
+        combined: args.combined,
+        format: args.format,
+    };
+    match &args.dst {
+        Some(dst) if !args.dry_run => sampler::sample(&args.src, dst, &options).await,
+        _ => sampler::dry_run(&args.src, &options).await,
+    }
+}
+
+async fn query(args: cli::QueryArgs) -> Result<()> {
+    let ctx = sampler::session(args.session.target_partitions, args.session.memory_limit)?;
+    query::query(
+        &ctx,
+        &args.src,
+        &args.sql,
+        args.format,
+        args.output.as_deref(),
+    )
+    .await
+}
 
 #[tokio::main]
 async fn main() {
-    let args = cli::Args::parse();
+    let cli = cli::Cli::parse();
 
-    let res = sampler::sample(&args.src, &args.dst).await;
+    let res = match cli.command {
+        cli::Command::Sample(args) => sample(args).await,
+        cli::Command::Query(args) => query(args).await,
+    };
 
     match res {
         Ok(_) => (),
-        Err(e) => eprintln!("Error: {}", e),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
     }
 }
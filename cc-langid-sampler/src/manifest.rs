@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use datafusion::error::{DataFusionError, Result};
+use serde::Serialize;
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::domains::HostFilter;
+use crate::multilingual::Multilingual;
+use crate::output::OutputFormat;
+use crate::policy::{FilterPolicy, Filters};
+use crate::sampler::SamplerOptions;
+use crate::sampling::Sampling;
+
+pub fn json_string<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value).map_err(|e| DataFusionError::External(Box::new(e)))
+}
+
+/// What was sampled for a language
+#[derive(Debug, Serialize)]
+pub struct LanguageSample {
+    /// Number of parquet files read
+    pub files: usize,
+    /// Number of rows written
+    pub rows: usize,
+    /// Rules applied to the language
+    pub filters: Filters,
+}
+
+/// Summary of a run, written next to the samples
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub tool: &'static str,
+    pub tool_version: &'static str,
+    pub seed: u64,
+    pub sampled_at: DateTime<Utc>,
+    pub combined: bool,
+    pub format: OutputFormat,
+    pub policy: FilterPolicy,
+    pub sampling: Sampling,
+    pub multilingual: Option<Multilingual>,
+    pub hosts: HostFilter,
+    /// Sampled languages, by language folder
+    pub languages: BTreeMap<String, LanguageSample>,
+    /// Languages that couldn't be sampled, with the error
+    pub failed: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    pub fn new(options: &SamplerOptions, sampled_at: DateTime<Utc>) -> Self {
+        Manifest {
+            tool: env!("CARGO_PKG_NAME"),
+            tool_version: env!("CARGO_PKG_VERSION"),
+            seed: options.seed,
+            sampled_at,
+            combined: options.combined,
+            format: options.format,
+            policy: options.policy.clone(),
+            sampling: options.sampling.clone(),
+            multilingual: options.multilingual.clone(),
+            hosts: options.hosts.clone(),
+            languages: BTreeMap::new(),
+            failed: BTreeMap::new(),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let manifest = serde_json::to_string_pretty(self)
+            .map_err(|e| DataFusionError::External(Box::new(e)))?;
+        fs::write(path, manifest)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+use datafusion::arrow::array::{Array, ArrayRef, AsArray, BooleanArray, ListArray, StringArray};
+use datafusion::arrow::datatypes::{DataType, Field, Float32Type};
+use datafusion::error::Result;
+use datafusion::logical_expr::{ColumnarValue, Volatility};
+use datafusion::prelude::*;
+use serde::Serialize;
+use std::{collections::BTreeMap, str::FromStr, sync::Arc};
+
+use crate::signals::normalize_lang;
+
+/// Selects documents whose lines are written in several languages, using the per-line
+/// language identification of `sentence_langs` and `sentences_probs`
+#[derive(Debug, Clone, Serialize)]
+pub struct Multilingual {
+    /// Minimum number of line languages a document needs
+    pub min_languages: usize,
+    /// Share of the identified lines a language needs to count towards `min_languages`
+    pub min_share: f64,
+    /// Lines identified with a lower probability are ignored
+    pub min_line_prob: f32,
+    /// Languages a document must all contain, e.g. `eng` and `hin`
+    pub required: Languages,
+}
+
+/// ISO 639-3 codes of languages
+pub type Languages = Vec<String>;
+
+/// Parses a language pair like `eng+hin`, normalized to ISO 639-3 codes
+pub fn parse_languages(languages: &str) -> Result<Languages, String> {
+    languages
+        .split('+')
+        .map(|lang| {
+            normalize_lang(lang)
+                .ok_or_else(|| "expected languages separated by `+`, like `eng+hin`".to_string())
+        })
+        .collect()
+}
+
+/// Parses a share of lines or a probability, which must be between 0 and 1
+pub fn parse_share<T: FromStr + PartialOrd + From<u8>>(value: &str) -> Result<T, String> {
+    match value.parse::<T>() {
+        Ok(share) if (T::from(0)..=T::from(1)).contains(&share) => Ok(share),
+        _ => Err(format!("expected a number between 0 and 1, got {}", value)),
+    }
+}
+
+/// Share of the identified lines of a document per language, most frequent first. Languages
+/// are normalized to ISO 639-3 codes, as `sentence_langs` holds fastText labels like `en`
+pub fn line_shares(
+    langs: &ListArray,
+    probs: &ListArray,
+    row: usize,
+    min_prob: f32,
+) -> Vec<(String, f64)> {
+    if langs.is_null(row) {
+        return vec![];
+    }
+    let line_langs = langs.value(row);
+    let line_langs = line_langs.as_string::<i32>();
+    let line_probs = match probs.is_null(row) {
+        true => None,
+        false => Some(probs.value(row)),
+    };
+    let line_probs = line_probs
+        .as_ref()
+        .map(|probs| probs.as_primitive::<Float32Type>());
+
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for (i, lang) in line_langs.iter().enumerate() {
+        let Some(lang) = lang.and_then(normalize_lang) else {
+            continue;
+        };
+        let prob = line_probs
+            .filter(|probs| i < probs.len() && probs.is_valid(i))
+            .map(|probs| probs.value(i));
+        if prob.is_some_and(|prob| prob < min_prob) {
+            continue;
+        }
+        *counts.entry(lang).or_default() += 1;
+    }
+
+    let total: usize = counts.values().sum();
+    let mut shares: Vec<(String, f64)> = counts
+        .into_iter()
+        .map(|(lang, count)| (lang, count as f64 / total as f64))
+        .collect();
+    shares.sort_by(|a, b| b.1.total_cmp(&a.1));
+    shares
+}
+
+/// Type of the list columns of the corpus
+pub fn list_type(item: DataType) -> DataType {
+    DataType::List(Arc::new(Field::new("item", item, true)))
+}
+
+impl Multilingual {
+    /// Whether a document with these line shares is selected
+    fn selects(&self, shares: &[(String, f64)]) -> bool {
+        let present: Vec<&str> = shares
+            .iter()
+            .filter(|(_, share)| *share >= self.min_share)
+            .map(|(lang, _)| lang.as_str())
+            .collect();
+        present.len() >= self.min_languages
+            && self
+                .required
+                .iter()
+                .all(|lang| present.contains(&lang.as_str()))
+    }
+
+    /// Keeps the multilingual documents and adds a `line_languages` column with their mixture,
+    /// like `eng:0.667,hin:0.333`
+    pub fn apply(&self, df: DataFrame) -> Result<DataFrame> {
+        let input_types = vec![list_type(DataType::Utf8), list_type(DataType::Float32)];
+
+        let selector = self.clone();
+        let is_multilingual = create_udf(
+            "is_multilingual",
+            input_types.clone(),
+            Arc::new(DataType::Boolean),
+            Volatility::Immutable,
+            Arc::new(move |args: &[ColumnarValue]| {
+                let args = ColumnarValue::values_to_arrays(args)?;
+                let (langs, probs) = (args[0].as_list::<i32>(), args[1].as_list::<i32>());
+                let selected: BooleanArray = (0..langs.len())
+                    .map(|row| {
+                        Some(selector.selects(&line_shares(
+                            langs,
+                            probs,
+                            row,
+                            selector.min_line_prob,
+                        )))
+                    })
+                    .collect();
+                Ok(ColumnarValue::Array(Arc::new(selected) as ArrayRef))
+            }),
+        );
+
+        let min_line_prob = self.min_line_prob;
+        let line_languages = create_udf(
+            "line_languages",
+            input_types,
+            Arc::new(DataType::Utf8),
+            Volatility::Immutable,
+            Arc::new(move |args: &[ColumnarValue]| {
+                let args = ColumnarValue::values_to_arrays(args)?;
+                let (langs, probs) = (args[0].as_list::<i32>(), args[1].as_list::<i32>());
+                let mixtures: StringArray = (0..langs.len())
+                    .map(|row| {
+                        let shares = line_shares(langs, probs, row, min_line_prob);
+                        let mixture: Vec<String> = shares
+                            .iter()
+                            .map(|(lang, share)| format!("{}:{:.3}", lang, share))
+                            .collect();
+                        (!mixture.is_empty()).then(|| mixture.join(","))
+                    })
+                    .collect();
+                Ok(ColumnarValue::Array(Arc::new(mixtures) as ArrayRef))
+            }),
+        );
+
+        let args = vec![col("sentence_langs"), col("sentences_probs")];
+        df.filter(is_multilingual.call(args.clone()))?
+            .with_column("line_languages", line_languages.call(args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{corpus, doc, float_lists, ids, string_lists, strings, Doc};
+
+    fn shares(langs: &[&str], probs: Option<&[f32]>, min_prob: f32) -> Vec<(String, f64)> {
+        let (langs, probs) = (string_lists([Some(langs)]), float_lists([probs]));
+        line_shares(&langs, &probs, 0, min_prob)
+    }
+
+    fn mixed(min_languages: usize, required: &str) -> Multilingual {
+        Multilingual {
+            min_languages,
+            min_share: 0.2,
+            min_line_prob: 0.5,
+            required: match required {
+                "" => vec![],
+                required => parse_languages(required).unwrap(),
+            },
+        }
+    }
+
+    fn share(lang: &str, share: f64) -> (String, f64) {
+        (lang.to_string(), share)
+    }
+
+    #[test]
+    fn language_pairs_are_normalized() {
+        assert_eq!(parse_languages("en+hi").unwrap(), vec!["eng", "hin"]);
+        assert_eq!(
+            parse_languages("eng+__label__hi").unwrap(),
+            vec!["eng", "hin"]
+        );
+        assert!(parse_languages("").is_err());
+        assert!(parse_languages("en+").is_err());
+    }
+
+    #[test]
+    fn shares_are_between_0_and_1() {
+        assert_eq!(parse_share::<f64>("0.25"), Ok(0.25));
+        assert_eq!(parse_share::<f32>("1"), Ok(1.0));
+        assert_eq!(parse_share::<f32>("0"), Ok(0.0));
+        assert!(parse_share::<f64>("-0.1").is_err());
+        assert!(parse_share::<f64>("1.5").is_err());
+        assert!(parse_share::<f32>("NaN").is_err());
+        assert!(parse_share::<f32>("half").is_err());
+    }
+
+    #[test]
+    fn line_shares_are_normalized_and_sorted() {
+        assert_eq!(
+            shares(&["hi", "en", "hi", "hin"], None, 0.0),
+            vec![share("hin", 0.75), share("eng", 0.25)]
+        );
+    }
+
+    #[test]
+    fn line_shares_ignore_unlikely_and_unidentified_lines() {
+        let langs = ["hi", "en", "en", ""];
+        let probs = [0.9, 0.3, 0.8, 0.9];
+        assert_eq!(
+            shares(&langs, Some(&probs), 0.5),
+            vec![share("eng", 0.5), share("hin", 0.5)]
+        );
+        // lines without a probability are kept
+        assert_eq!(
+            shares(&langs, Some(&probs[..1]), 0.5),
+            vec![share("eng", 2.0 / 3.0), share("hin", 1.0 / 3.0)]
+        );
+        let (langs, probs) = (string_lists([None]), float_lists([None]));
+        assert!(line_shares(&langs, &probs, 0, 0.0).is_empty());
+    }
+
+    #[test]
+    fn selects_needs_enough_languages_with_enough_lines() {
+        let shares = vec![share("hin", 0.7), share("eng", 0.2), share("urd", 0.1)];
+        assert!(mixed(2, "").selects(&shares));
+        assert!(!mixed(3, "").selects(&shares));
+        assert!(mixed(2, "en+hi").selects(&shares));
+        assert!(!mixed(1, "ur").selects(&shares));
+        assert!(!mixed(1, "").selects(&[]));
+    }
+
+    #[tokio::test]
+    async fn apply_keeps_mixed_documents_with_their_mixture() {
+        let docs = vec![
+            Doc {
+                line_langs: Some(vec!["hi", "hi", "en"]),
+                line_probs: Some(vec![0.9, 0.9, 0.9]),
+                ..doc("mixed")
+            },
+            Doc {
+                line_langs: Some(vec!["hi", "hi", "en"]),
+                line_probs: Some(vec![0.9, 0.9, 0.1]),
+                ..doc("unlikely")
+            },
+            Doc {
+                line_langs: Some(vec!["en", "en"]),
+                ..doc("english")
+            },
+            doc("unidentified"),
+        ];
+        let df = mixed(2, "en+hi").apply(corpus(&docs)).unwrap();
+        assert_eq!(ids(df.clone()).await, vec!["mixed"]);
+        assert_eq!(
+            strings(df, "line_languages").await,
+            vec![Some("hin:0.667,eng:0.333".to_string())]
+        );
+    }
+}
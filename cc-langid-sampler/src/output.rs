@@ -0,0 +1,86 @@
+use clap::ValueEnum;
+use datafusion::arrow::array::{AsArray, RecordBatch};
+use datafusion::arrow::datatypes::{DataType, UInt64Type};
+use datafusion::config::{CsvOptions, TableParquetOptions};
+use datafusion::dataframe::DataFrameWriteOptions;
+use datafusion::error::Result;
+use datafusion::prelude::*;
+use serde::Serialize;
+use std::path::Path;
+
+/// File format samples and query results are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Parquet,
+    /// List columns are written as strings like `[adult, porn]`
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// Number of rows written, from the result of a `write_parquet`, `write_csv` or `write_json`
+fn written_rows(result: &[RecordBatch]) -> usize {
+    result
+        .iter()
+        .flat_map(|batch| batch.column(0).as_primitive::<UInt64Type>().values().iter())
+        .sum::<u64>() as usize
+}
+
+/// Casts the list columns of a DataFrame to strings, as CSV can't hold lists
+fn flatten_lists(df: DataFrame) -> Result<DataFrame> {
+    let columns: Vec<Expr> = df
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| match field.data_type() {
+            DataType::List(_) | DataType::LargeList(_) => {
+                cast(col(field.name()), DataType::Utf8).alias(field.name())
+            }
+            _ => col(field.name()),
+        })
+        .collect();
+    df.select(columns)
+}
+
+/// Writes a DataFrame to `path` and returns the number of rows written. For parquet files,
+/// `metadata` is a key and a JSON value added to the file metadata.
+pub async fn write(
+    df: DataFrame,
+    path: &Path,
+    format: OutputFormat,
+    metadata: Option<(&str, String)>,
+) -> Result<usize> {
+    let path = path.to_str().unwrap();
+    let options = DataFrameWriteOptions::new();
+    let result = match format {
+        OutputFormat::Parquet => {
+            let mut writer_options = TableParquetOptions::new();
+            if let Some((key, value)) = metadata {
+                writer_options
+                    .key_value_metadata
+                    .insert(key.to_string(), Some(value));
+            }
+            df.write_parquet(path, options, Some(writer_options))
+                .await?
+        }
+        OutputFormat::Csv => {
+            let csv_options = CsvOptions::default().with_has_header(true);
+            flatten_lists(df)?
+                .write_csv(path, options, Some(csv_options))
+                .await?
+        }
+        OutputFormat::Jsonl => df.write_json(path, options, None).await?,
+    };
+    Ok(written_rows(&result))
+}
@@ -0,0 +1,384 @@
+use datafusion::error::{DataFusionError, Result};
+use datafusion::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// UT1 categories dropped when no policy file is given
+const DANGEROUS_CATEGORIES: [&str; 12] = [
+    "agressif",
+    "adult",
+    "cryptojacking",
+    "dangerous_material",
+    "phishing",
+    "porn",
+    "warez",
+    "ddos",
+    "hacking",
+    "malware",
+    "mixed_adult",
+    "sect",
+];
+
+/// What to do with documents that have quality warnings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QualityWarnings {
+    /// Drop documents with a `quality_warnings` list, even an empty one
+    DropAny,
+    /// Keep documents with quality warnings, except the ones listed in `deny_quality_warnings`
+    Keep,
+}
+
+/// Rules a document has to pass to be sampled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Filters {
+    /// Documents with any of these UT1 categories are dropped. Documents without categories
+    /// are dropped too, unless `keep_uncategorized` is set
+    pub deny_categories: Vec<String>,
+    /// Keep the documents without UT1 categories when `deny_categories` is not empty
+    pub keep_uncategorized: bool,
+    /// If not empty, only documents with at least one of these UT1 categories are kept
+    pub allow_categories: Vec<String>,
+    pub quality_warnings: QualityWarnings,
+    /// Documents with any of these quality warnings are dropped
+    pub deny_quality_warnings: Vec<String>,
+    /// Highest `harmful_pp` kept. Documents without a perplexity are kept
+    pub max_harmful_pp: Option<f32>,
+    /// Lowest `harmful_pp` kept. Documents without a perplexity are kept
+    pub min_harmful_pp: Option<f32>,
+    /// Lowest `identified_doc_prob` kept
+    pub min_doc_prob: Option<f32>,
+    /// Highest `identified_doc_prob` kept
+    pub max_doc_prob: Option<f32>,
+    /// SQL predicates documents must all satisfy, e.g. `length(content) > 500`
+    pub predicates: Vec<String>,
+}
+
+impl Default for Filters {
+    fn default() -> Self {
+        Filters {
+            deny_categories: DANGEROUS_CATEGORIES.iter().map(|c| c.to_string()).collect(),
+            keep_uncategorized: false,
+            allow_categories: vec![],
+            quality_warnings: QualityWarnings::DropAny,
+            deny_quality_warnings: vec![],
+            max_harmful_pp: None,
+            min_harmful_pp: None,
+            min_doc_prob: None,
+            max_doc_prob: None,
+            predicates: vec![],
+        }
+    }
+}
+
+/// Rules of a language that replace the default ones. Fields that are not set are taken from
+/// the defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FiltersOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deny_categories: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_uncategorized: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_categories: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality_warnings: Option<QualityWarnings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deny_quality_warnings: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_harmful_pp: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_harmful_pp: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_doc_prob: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_doc_prob: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predicates: Option<Vec<String>>,
+}
+
+/// Filter policy of a run, read from a TOML file:
+///
+/// ```toml
+/// deny_categories = ["adult", "porn", "phishing"]
+/// keep_uncategorized = true
+/// quality_warnings = "keep"
+/// deny_quality_warnings = ["tiny"]
+/// max_harmful_pp = 200.0
+/// predicates = ["length(content) > 500"]
+///
+/// [languages.tha]
+/// min_doc_prob = 0.5
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterPolicy {
+    #[serde(flatten)]
+    pub default: Filters,
+    /// Overrides by language folder name
+    pub languages: BTreeMap<String, FiltersOverride>,
+}
+
+impl FilterPolicy {
+    pub fn load(path: &Path) -> Result<FilterPolicy> {
+        let policy = fs::read_to_string(path)?;
+        toml::from_str(&policy).map_err(|e| {
+            DataFusionError::Configuration(format!(
+                "Invalid filter policy {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Rules applied to a language: the defaults with the language overrides on top
+    pub fn filters(&self, lang: &str) -> Filters {
+        let mut filters = self.default.clone();
+        let Some(over) = self.languages.get(lang) else {
+            return filters;
+        };
+        let over = over.clone();
+        if let Some(deny_categories) = over.deny_categories {
+            filters.deny_categories = deny_categories;
+        }
+        filters.keep_uncategorized = over
+            .keep_uncategorized
+            .unwrap_or(filters.keep_uncategorized);
+        if let Some(allow_categories) = over.allow_categories {
+            filters.allow_categories = allow_categories;
+        }
+        if let Some(quality_warnings) = over.quality_warnings {
+            filters.quality_warnings = quality_warnings;
+        }
+        if let Some(deny_quality_warnings) = over.deny_quality_warnings {
+            filters.deny_quality_warnings = deny_quality_warnings;
+        }
+        filters.max_harmful_pp = over.max_harmful_pp.or(filters.max_harmful_pp);
+        filters.min_harmful_pp = over.min_harmful_pp.or(filters.min_harmful_pp);
+        filters.min_doc_prob = over.min_doc_prob.or(filters.min_doc_prob);
+        filters.max_doc_prob = over.max_doc_prob.or(filters.max_doc_prob);
+        if let Some(predicates) = over.predicates {
+            filters.predicates = predicates;
+        }
+        filters
+    }
+}
+
+fn string_array(values: &[String]) -> Expr {
+    make_array(values.iter().map(|value| lit(value.as_str())).collect())
+}
+
+/// Keeps documents whose list column `column` contains none of `values`. Documents where the
+/// list is null are kept.
+fn has_none(column: &str, values: &[String]) -> Expr {
+    col(column)
+        .is_null()
+        .or(array_has_any(col(column), string_array(values)).not())
+}
+
+impl Filters {
+    /// Applies the rules to a DataFrame of documents
+    pub fn apply(&self, df: DataFrame) -> Result<DataFrame> {
+        let mut df = df;
+        if !self.deny_categories.is_empty() {
+            df = df.filter(match self.keep_uncategorized {
+                true => has_none("categories", &self.deny_categories),
+                // null when there are no categories, which drops the document
+                false => {
+                    array_has_any(col("categories"), string_array(&self.deny_categories)).not()
+                }
+            })?;
+        }
+        if !self.allow_categories.is_empty() {
+            df = df.filter(array_has_any(
+                col("categories"),
+                string_array(&self.allow_categories),
+            ))?;
+        }
+        if self.quality_warnings == QualityWarnings::DropAny {
+            df = df.filter(col("quality_warnings").is_null())?;
+        }
+        if !self.deny_quality_warnings.is_empty() {
+            df = df.filter(has_none("quality_warnings", &self.deny_quality_warnings))?;
+        }
+
+        let harmful_pp = |bound: Expr| col("harmful_pp").is_null().or(bound);
+        if let Some(max) = self.max_harmful_pp {
+            df = df.filter(harmful_pp(col("harmful_pp").lt_eq(lit(max))))?;
+        }
+        if let Some(min) = self.min_harmful_pp {
+            df = df.filter(harmful_pp(col("harmful_pp").gt_eq(lit(min))))?;
+        }
+        if let Some(min) = self.min_doc_prob {
+            df = df.filter(col("identified_doc_prob").gt_eq(lit(min)))?;
+        }
+        if let Some(max) = self.max_doc_prob {
+            df = df.filter(col("identified_doc_prob").lt_eq(lit(max)))?;
+        }
+
+        for predicate in &self.predicates {
+            let predicate = df.parse_sql_expr(predicate)?;
+            df = df.filter(predicate)?;
+        }
+        Ok(df)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{corpus, doc, ids, Doc};
+
+    fn docs() -> Vec<Doc> {
+        vec![
+            Doc {
+                categories: Some(vec!["news"]),
+                harmful_pp: Some(50.0),
+                ..doc("news")
+            },
+            Doc {
+                categories: Some(vec!["news", "adult"]),
+                ..doc("adult")
+            },
+            Doc {
+                quality_warnings: Some(vec![]),
+                harmful_pp: Some(500.0),
+                ..doc("uncategorized")
+            },
+            Doc {
+                categories: Some(vec!["blog"]),
+                quality_warnings: Some(vec!["tiny"]),
+                ..doc("tiny")
+            },
+            Doc {
+                categories: Some(vec!["blog"]),
+                quality_warnings: Some(vec!["noisy"]),
+                prob: 0.4,
+                ..doc("noisy")
+            },
+        ]
+    }
+
+    async fn kept(filters: Filters) -> Vec<String> {
+        let mut kept = ids(filters.apply(corpus(&docs())).unwrap()).await;
+        kept.sort();
+        kept
+    }
+
+    fn no_filters() -> Filters {
+        Filters {
+            deny_categories: vec![],
+            quality_warnings: QualityWarnings::Keep,
+            ..Filters::default()
+        }
+    }
+
+    #[test]
+    fn languages_override_the_defaults() {
+        let policy: FilterPolicy = toml::from_str(
+            r#"
+            deny_categories = ["adult"]
+            keep_uncategorized = true
+            max_harmful_pp = 200.0
+
+            [languages.tha]
+            min_doc_prob = 0.5
+            max_harmful_pp = 300.0
+            keep_uncategorized = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(policy.filters("fra"), policy.default);
+        assert_eq!(policy.default.quality_warnings, QualityWarnings::DropAny);
+
+        let tha = policy.filters("tha");
+        assert_eq!(tha.deny_categories, vec!["adult".to_string()]);
+        assert!(!tha.keep_uncategorized);
+        assert_eq!(tha.max_harmful_pp, Some(300.0));
+        assert_eq!(tha.min_doc_prob, Some(0.5));
+    }
+
+    #[test]
+    fn unknown_rules_are_rejected() {
+        assert!(toml::from_str::<FilterPolicy>("max_harmful = 1.0").is_err());
+        assert!(toml::from_str::<FilterPolicy>("[languages.tha]\nmin_prob = 0.5").is_err());
+    }
+
+    #[tokio::test]
+    async fn denied_categories_drop_uncategorized_documents_by_default() {
+        let filters = Filters {
+            quality_warnings: QualityWarnings::Keep,
+            ..Filters::default()
+        };
+        assert_eq!(kept(filters.clone()).await, vec!["news", "noisy", "tiny"]);
+
+        let filters = Filters {
+            keep_uncategorized: true,
+            ..filters
+        };
+        assert_eq!(
+            kept(filters).await,
+            vec!["news", "noisy", "tiny", "uncategorized"]
+        );
+    }
+
+    #[tokio::test]
+    async fn allowed_categories_are_required() {
+        let filters = Filters {
+            allow_categories: vec!["blog".to_string()],
+            ..no_filters()
+        };
+        assert_eq!(kept(filters).await, vec!["noisy", "tiny"]);
+    }
+
+    #[tokio::test]
+    async fn drop_any_drops_empty_quality_warnings() {
+        let filters = Filters {
+            quality_warnings: QualityWarnings::DropAny,
+            ..no_filters()
+        };
+        assert_eq!(kept(filters).await, vec!["adult", "news"]);
+    }
+
+    #[tokio::test]
+    async fn keep_only_drops_denied_quality_warnings() {
+        let filters = Filters {
+            deny_quality_warnings: vec!["tiny".to_string()],
+            ..no_filters()
+        };
+        assert_eq!(
+            kept(filters).await,
+            vec!["adult", "news", "noisy", "uncategorized"]
+        );
+    }
+
+    #[tokio::test]
+    async fn harmful_pp_bounds_keep_documents_without_perplexity() {
+        let filters = Filters {
+            max_harmful_pp: Some(200.0),
+            ..no_filters()
+        };
+        assert_eq!(kept(filters).await, vec!["adult", "news", "noisy", "tiny"]);
+
+        let filters = Filters {
+            min_harmful_pp: Some(200.0),
+            ..no_filters()
+        };
+        assert_eq!(
+            kept(filters).await,
+            vec!["adult", "noisy", "tiny", "uncategorized"]
+        );
+    }
+
+    #[tokio::test]
+    async fn doc_prob_bounds_and_predicates_apply() {
+        let filters = Filters {
+            min_doc_prob: Some(0.5),
+            predicates: vec!["warc_record_id <> 'adult'".to_string()],
+            ..no_filters()
+        };
+        assert_eq!(kept(filters).await, vec!["news", "tiny", "uncategorized"]);
+    }
+}
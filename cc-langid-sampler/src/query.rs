@@ -0,0 +1,104 @@
+use datafusion::error::{DataFusionError, Result};
+use datafusion::prelude::*;
+use std::path::Path;
+
+use crate::output::{self, OutputFormat};
+use crate::sampler::{lang_folders, scan_lang, union_files};
+
+/// Name of the table holding every language, with a `lang` column
+const CORPUS_TABLE: &str = "corpus";
+
+/// Registers every lang folder of `src` as a table named after the folder, and all of them as
+/// one `corpus` table with a `lang` column
+async fn register_tables(ctx: &SessionContext, src: &Path) -> Result<()> {
+    let mut corpus = vec![];
+    for lang in lang_folders(src) {
+        let files = scan_lang(ctx, &lang).await?;
+        let language = lang.file_name().to_string_lossy().to_string();
+        let df = union_files(ctx, &files, None)?;
+        ctx.register_table(language.as_str(), df.into_view())?;
+        corpus.extend(files);
+    }
+    if corpus.is_empty() {
+        return Err(DataFusionError::Plan(format!(
+            "No language folders found in {}",
+            src.display()
+        )));
+    }
+    let corpus = union_files(ctx, &corpus, Some("lang"))?;
+    ctx.register_table(CORPUS_TABLE, corpus.into_view())?;
+    Ok(())
+}
+
+/// Format matching the extension of an output file
+fn format_from_path(path: &Path) -> Option<OutputFormat> {
+    match path.extension()?.to_str()? {
+        "csv" => Some(OutputFormat::Csv),
+        "parquet" => Some(OutputFormat::Parquet),
+        "jsonl" | "json" => Some(OutputFormat::Jsonl),
+        _ => None,
+    }
+}
+
+/// Runs a SQL query over the language folders of `src`. Results are printed as a table, or
+/// written to `output` in the given format, or else the one matching its extension.
+pub async fn query(
+    ctx: &SessionContext,
+    src: &Path,
+    sql: &str,
+    format: Option<OutputFormat>,
+    output: Option<&Path>,
+) -> Result<()> {
+    register_tables(ctx, src).await?;
+    let df = ctx.sql(sql).await?;
+
+    let Some(output) = output else {
+        return df.show().await;
+    };
+    let format = format.or_else(|| format_from_path(output)).ok_or_else(|| {
+        DataFusionError::Configuration(format!(
+            "Can't tell the format of {} from its extension, use --format",
+            output.display()
+        ))
+    })?;
+    output::write(df, output, format, None).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::fixtures::{corpus, doc, strings, temp_dir, write_parquet};
+
+    #[tokio::test]
+    async fn source_files_keep_their_provenance() {
+        let src = temp_dir("query-source-file");
+        let shard = src.join("fr").join("fr_part_1.parquet");
+        write_parquet(corpus(&[doc("<urn:uuid:1>")]), &shard).await;
+        let sample = corpus(&[doc("<urn:uuid:2>")])
+            .with_column("source_file", lit("/oscar/de/de_part_7.parquet"))
+            .unwrap();
+        write_parquet(sample, &src.join("de").join("sample.parquet")).await;
+
+        let ctx = SessionContext::new();
+        register_tables(&ctx, &src).await.unwrap();
+        let df = ctx
+            .sql("SELECT lang, source_file FROM corpus ORDER BY lang")
+            .await
+            .unwrap();
+        assert_eq!(
+            strings(df.clone(), "lang").await,
+            [Some("de".to_string()), Some("fr".to_string())]
+        );
+        assert_eq!(
+            strings(df, "source_file").await,
+            [
+                Some("/oscar/de/de_part_7.parquet".to_string()),
+                Some(shard.to_str().unwrap().to_string()),
+            ]
+        );
+        fs::remove_dir_all(&src).unwrap();
+    }
+}
@@ -1,14 +1,80 @@
-use datafusion::dataframe::DataFrameWriteOptions;
-use datafusion::error::Result;
+use chrono::{DateTime, SubsecRound, Utc};
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::datatypes::Field;
+use datafusion::common::{Column, DFSchema, ScalarValue};
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::memory_pool::FairSpillPool;
+use datafusion::execution::runtime_env::{RuntimeConfig, RuntimeEnv};
+use datafusion::logical_expr::{LogicalPlan, Union};
 use datafusion::prelude::*;
-use std::{path::PathBuf, vec};
+use futures::{stream, StreamExt};
+use std::{collections::HashMap, fs, path::Path, sync::Arc, vec};
 
-use rand::{seq::SliceRandom, thread_rng};
 use walkdir::{DirEntry, WalkDir};
 
-async fn process_lang(lang: DirEntry, dst: &PathBuf) -> Result<()> {
-    let language = lang.path().components().last().unwrap();
+use crate::domains::HostFilter;
+use crate::manifest::{json_string, LanguageSample, Manifest};
+use crate::multilingual::Multilingual;
+use crate::output::{self, OutputFormat};
+use crate::policy::{FilterPolicy, Filters};
+use crate::sampling::Sampling;
 
+/// Name of the file written by `--combined` in the destination folder, without extension
+const COMBINED_FILE: &str = "sample";
+
+/// Name of the manifest written in the destination folder
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// How a run samples the language folders
+pub struct SamplerOptions {
+    pub seed: u64,
+    /// Lang folders to sample, all of them when empty
+    pub languages: Vec<String>,
+    /// Lang folders not to sample
+    pub exclude_languages: Vec<String>,
+    pub policy: FilterPolicy,
+    pub sampling: Sampling,
+    /// Only sample documents written in several languages
+    pub multilingual: Option<Multilingual>,
+    /// Deny lists and per-host caps
+    pub hosts: HostFilter,
+    /// Number of languages sampled at the same time
+    pub concurrency: usize,
+    /// Number of partitions DataFusion splits the work of a query in
+    pub target_partitions: usize,
+    /// Memory shared by all the languages sampled at the same time, in bytes. Sorts spill to
+    /// disk when it runs out. Unbounded when not set
+    pub memory_limit: Option<usize>,
+    /// Write every language to one file instead of one file per language
+    pub combined: bool,
+    pub format: OutputFormat,
+}
+
+/// Session shared by every language of a run, splitting queries in `target_partitions` and
+/// spilling sorts to disk past `memory_limit` bytes
+pub fn session(target_partitions: usize, memory_limit: Option<usize>) -> Result<SessionContext> {
+    let config = SessionConfig::new().with_target_partitions(target_partitions);
+    let mut runtime = RuntimeConfig::new();
+    if let Some(limit) = memory_limit {
+        runtime = runtime.with_memory_pool(Arc::new(FairSpillPool::new(limit)));
+    }
+    Ok(SessionContext::new_with_config_rt(
+        config,
+        Arc::new(RuntimeEnv::new(runtime)?),
+    ))
+}
+
+/// A parquet file of a lang folder, read into a DataFrame
+pub struct LangFile {
+    pub path: String,
+    /// Name of the lang folder holding the file
+    pub language: String,
+    df: DataFrame,
+}
+
+/// Reads the schema of every parquet file of a lang folder, sorted by path
+pub async fn scan_lang(ctx: &SessionContext, lang: &DirEntry) -> Result<Vec<LangFile>> {
+    let language = lang.file_name().to_string_lossy().to_string();
     let mut file_paths: Vec<String> = WalkDir::new(lang.path())
         .into_iter()
         .filter_map(Result::ok)
@@ -17,69 +83,456 @@ async fn process_lang(lang: DirEntry, dst: &PathBuf) -> Result<()> {
         .map(|e| e.path().to_str().unwrap().to_owned())
         .collect();
 
-    file_paths.shuffle(&mut thread_rng());
+    file_paths.sort();
+    if file_paths.is_empty() {
+        return Err(DataFusionError::Plan(format!(
+            "No parquet files found for {}",
+            language
+        )));
+    }
+
+    let mut files = vec![];
+    for path in file_paths {
+        let df = ctx
+            .read_parquet(path.as_str(), ParquetReadOptions::default())
+            .await?;
+        files.push(LangFile {
+            path,
+            language: language.clone(),
+            df,
+        });
+    }
+    Ok(files)
+}
 
-    let ctx = SessionContext::new();
-    // read parque files into a DataFrame
-    let df = ctx
-        .read_parquet(file_paths, ParquetReadOptions::default())
-        .await?;
+/// Unions DataFrames into one, matching their columns by name. Columns missing from some of
+/// them are null there. The DataFrames are the inputs of a single union, as chaining
+/// `DataFrame::union` nests a union per DataFrame and matches columns by position.
+pub fn union_by_name(ctx: &SessionContext, dfs: Vec<DataFrame>) -> Result<DataFrame> {
+    // columns of all the DataFrames, in the order they are first found
+    let mut fields: Vec<Field> = vec![];
+    for df in &dfs {
+        for field in df.schema().fields() {
+            match fields.iter().find(|seen| seen.name() == field.name()) {
+                Some(seen) if seen.data_type() != field.data_type() => {
+                    return Err(DataFusionError::Plan(format!(
+                        "Column {} is both {} and {}",
+                        field.name(),
+                        seen.data_type(),
+                        field.data_type()
+                    )));
+                }
+                Some(_) => {}
+                None => fields.push(field.as_ref().clone()),
+            }
+        }
+    }
+
+    let mut inputs = vec![];
+    for df in dfs {
+        let mut columns = vec![];
+        for field in &fields {
+            let column = match df.schema().has_column_with_unqualified_name(field.name()) {
+                true => Expr::Column(Column::new_unqualified(field.name())),
+                false => lit(ScalarValue::try_from(field.data_type())?),
+            };
+            columns.push(column.alias(field.name()));
+        }
+        inputs.push(Arc::new(df.select(columns)?.into_unoptimized_plan()));
+    }
+
+    let first = inputs
+        .first()
+        .ok_or_else(|| DataFusionError::Plan("Nothing to union".to_string()))?;
+    let fields = first
+        .schema()
+        .iter()
+        .map(|(qualifier, field)| {
+            let field = field.as_ref().clone().with_nullable(true);
+            (qualifier.cloned(), Arc::new(field))
+        })
+        .collect();
+    let schema = DFSchema::new_with_metadata(fields, HashMap::new())?;
+    let plan = LogicalPlan::Union(Union {
+        inputs,
+        schema: Arc::new(schema),
+    });
+    Ok(DataFrame::new(ctx.state(), plan))
+}
 
-    // filter out rows with quality_warnings
-    let dangerous_categories = make_array(vec![
-        lit("agressif"),
-        lit("adult"),
-        lit("cryptojacking"),
-        lit("dangerous_material"),
-        lit("phishing"),
-        lit("porn"),
-        lit("warez"),
-        lit("ddos"),
-        lit("hacking"),
-        lit("malware"),
-        lit("mixed_adult"),
-        lit("sect"),
-    ]);
-
-    let df = df.filter(col("quality_warnings").is_null())?;
-    let df = df.filter(array_has_any(col("categories"), dangerous_categories).not())?;
-    let df = df.limit(0, Some(1000))?;
-
-    let mut dst = dst.clone();
+/// Unions parquet files into one DataFrame, with a `source_file` column holding the path of
+/// the file of each row and, if `lang_column` is set, a column with the name of its lang folder.
+/// Files that already have a `source_file` column, like samples written with provenance
+/// columns, keep theirs. Columns are matched by name.
+pub fn union_files(
+    ctx: &SessionContext,
+    files: &[LangFile],
+    lang_column: Option<&str>,
+) -> Result<DataFrame> {
+    let mut dfs = vec![];
+    for file in files {
+        let mut df = file.df.clone();
+        if !df.schema().has_column_with_unqualified_name("source_file") {
+            df = df.with_column("source_file", lit(file.path.as_str()))?;
+        }
+        if let Some(lang_column) = lang_column {
+            df = df.with_column(lang_column, lit(file.language.as_str()))?;
+        }
+        dfs.push(df);
+    }
+    union_by_name(ctx, dfs)
+}
+
+/// Reads the parquet files of a lang folder into a DataFrame, with a `source_file` column
+/// holding the path of the file of each row. Returns the number of files read too.
+pub async fn read_lang(ctx: &SessionContext, lang: &DirEntry) -> Result<(DataFrame, usize)> {
+    let files = scan_lang(ctx, lang).await?;
+    Ok((union_files(ctx, &files, None)?, files.len()))
+}
+
+/// Keeps the documents of a language that pass the filter policy, the multilingual selection
+/// and the host rules. Returns the filter policy rules of the language too.
+fn select(df: DataFrame, language: &str, options: &SamplerOptions) -> Result<(DataFrame, Filters)> {
+    let filters = options.policy.filters(language);
+    let mut df = filters.apply(df)?;
+    if let Some(multilingual) = &options.multilingual {
+        df = multilingual.apply(df)?;
+    }
+    if options.hosts.is_set() {
+        df = options.hosts.apply(df, options.seed)?;
+    }
+    Ok((df, filters))
+}
+
+/// Builds the sample of a language: the rows of its parquet files passing the filters and
+/// picked by the sampling, with the provenance columns `sample_lang`, `source_file`,
+/// `sample_seed` and `sampled_at`.
+async fn lang_sample(
+    ctx: &SessionContext,
+    lang: &DirEntry,
+    options: &SamplerOptions,
+    sampled_at: DateTime<Utc>,
+) -> Result<(DataFrame, Filters, usize)> {
+    let language = lang.file_name().to_string_lossy().to_string();
+
+    let (df, files) = read_lang(ctx, lang).await?;
+    let (df, filters) = select(df, &language, options)?;
+    let df = options.sampling.apply(df, options.seed)?;
+
+    let df = df
+        .with_column("sample_lang", lit(language.as_str()))?
+        .with_column("sample_seed", lit(options.seed))?
+        .with_column(
+            "sampled_at",
+            lit(ScalarValue::TimestampMicrosecond(
+                Some(sampled_at.timestamp_micros()),
+                Some("UTC".into()),
+            )),
+        )?;
+    Ok((df, filters, files))
+}
+
+/// Samples a language and writes it to `dst/{lang}.{format}`
+async fn process_lang(
+    ctx: &SessionContext,
+    lang: &DirEntry,
+    dst: &Path,
+    options: &SamplerOptions,
+    sampled_at: DateTime<Utc>,
+) -> Result<LanguageSample> {
+    let (df, filters, files) = lang_sample(ctx, lang, options, sampled_at).await?;
+
+    let mut dst = dst.to_path_buf();
 
     dst.push(format!(
         "{}.{}",
-        language.as_os_str().to_str().unwrap(),
-        "parquet"
+        lang.file_name().to_str().unwrap(),
+        options.format.extension()
     ));
 
-    // stream the contents of the DataFrame to the `example.parquet` file
-    let result = df
-        .write_parquet(
-            dst.to_str().unwrap(),
-            DataFrameWriteOptions::new(),
-            None, // writer_options
-        )
-        .await;
-    if result.is_err() {
-        eprintln!("Error writing parquet file: {:?}", result);
-    }
-    Ok(())
+    // stream the contents of the DataFrame to the `{lang}.{format}` file, echoing the rules
+    // applied to the language into the parquet metadata
+    let metadata = ("filter_policy", json_string(&filters)?);
+    let rows = output::write(df, &dst, options.format, Some(metadata)).await?;
+    Ok(LanguageSample {
+        files,
+        rows,
+        filters,
+    })
 }
 
-pub async fn sample(src: &PathBuf, dst: &PathBuf) -> Result<()> {
-    // find all the lang folders containing the parquet files in the src folder
-    let folder_paths: Vec<DirEntry> = WalkDir::new(src)
+/// Rows of a language kept in memory for a combined output, and what was sampled
+type LangResult = Result<(Vec<RecordBatch>, LanguageSample)>;
+
+/// Samples a language and keeps its rows in memory, to be written with the other languages
+async fn collect_lang(
+    ctx: &SessionContext,
+    lang: &DirEntry,
+    options: &SamplerOptions,
+    sampled_at: DateTime<Utc>,
+) -> LangResult {
+    let (df, filters, files) = lang_sample(ctx, lang, options, sampled_at).await?;
+    let batches = df.collect().await?;
+    let rows = batches.iter().map(RecordBatch::num_rows).sum();
+    Ok((
+        batches,
+        LanguageSample {
+            files,
+            rows,
+            filters,
+        },
+    ))
+}
+
+/// Finds all the lang folders containing the parquet files in the src folder
+pub fn lang_folders(src: &Path) -> Vec<DirEntry> {
+    WalkDir::new(src)
         .min_depth(1)
         .max_depth(1)
         .into_iter()
         .filter_map(Result::ok)
         .filter(|e| e.file_type().is_dir())
+        .collect()
+}
+
+/// Lang folders of `src` selected by the include and exclude lists, sorted by name
+fn selected_folders(src: &Path, options: &SamplerOptions) -> Vec<DirEntry> {
+    let mut folders: Vec<DirEntry> = lang_folders(src)
+        .into_iter()
+        .filter(|lang| {
+            let language = lang.file_name().to_string_lossy().to_string();
+            (options.languages.is_empty() || options.languages.contains(&language))
+                && !options.exclude_languages.contains(&language)
+        })
         .collect();
+    folders.sort_by_key(|lang| lang.file_name().to_owned());
+
+    for language in &options.languages {
+        if !folders
+            .iter()
+            .any(|lang| lang.file_name().to_string_lossy() == *language)
+        {
+            eprintln!(
+                "Warning: no lang folder named {} in {}",
+                language,
+                src.display()
+            );
+        }
+    }
+    folders
+}
+
+/// Documents of a language, counted without sampling
+struct LanguageCount {
+    language: String,
+    files: usize,
+    /// Documents in the parquet files
+    documents: usize,
+    /// Documents passing the filters, that sampling picks from
+    selected: usize,
+}
+
+async fn count_lang(
+    ctx: &SessionContext,
+    lang: &DirEntry,
+    options: &SamplerOptions,
+) -> Result<LanguageCount> {
+    let language = lang.file_name().to_string_lossy().to_string();
+    let (df, files) = read_lang(ctx, lang).await?;
+    let documents = df.clone().count().await?;
+    let (df, _) = select(df, &language, options)?;
+    Ok(LanguageCount {
+        language,
+        files,
+        documents,
+        selected: df.count().await?,
+    })
+}
+
+/// Counts the documents of each selected language, and the ones passing the filters, without
+/// sampling or writing anything
+pub async fn dry_run(src: &Path, options: &SamplerOptions) -> Result<()> {
+    let folder_paths = selected_folders(src, options);
+    let ctx = session(options.target_partitions, options.memory_limit)?;
+
+    let mut counts: Vec<LanguageCount> = stream::iter(&folder_paths)
+        .map(|lang| {
+            let ctx = &ctx;
+            async move {
+                let res = count_lang(ctx, lang, options).await;
+                if let Err(e) = &res {
+                    eprintln!(
+                        "Error counting {}: {}",
+                        lang.file_name().to_string_lossy(),
+                        e
+                    );
+                }
+                res.ok()
+            }
+        })
+        .buffered(options.concurrency.max(1))
+        .filter_map(std::future::ready)
+        .collect()
+        .await;
+    counts.sort_by(|a, b| a.language.cmp(&b.language));
+
+    println!(
+        "{:<16} {:>6} {:>12} {:>12}",
+        "language", "files", "documents", "selected"
+    );
+    for count in &counts {
+        println!(
+            "{:<16} {:>6} {:>12} {:>12}",
+            count.language, count.files, count.documents, count.selected
+        );
+    }
+    println!(
+        "{} languages, {} documents, {} selected",
+        counts.len(),
+        counts.iter().map(|count| count.documents).sum::<usize>(),
+        counts.iter().map(|count| count.selected).sum::<usize>()
+    );
 
-    //iterate over the lang folders in parallel
-    for lang in folder_paths {
-        process_lang(lang, dst).await?;
+    if counts.len() < folder_paths.len() {
+        return Err(DataFusionError::Execution(format!(
+            "{} of {} languages failed",
+            folder_paths.len() - counts.len(),
+            folder_paths.len()
+        )));
     }
     Ok(())
 }
+
+pub async fn sample(src: &Path, dst: &Path, options: &SamplerOptions) -> Result<()> {
+    let folder_paths = selected_folders(src, options);
+    // the manifest is written even when no language could be sampled
+    fs::create_dir_all(dst)?;
+
+    let ctx = session(options.target_partitions, options.memory_limit)?;
+    let sampled_at = Utc::now().trunc_subsecs(6);
+
+    // sample up to `concurrency` lang folders at a time, a failing language doesn't stop the
+    // others. Results are kept in folder order so the combined file only depends on the seed
+    let results: Vec<(String, LangResult)> = stream::iter(&folder_paths)
+        .map(|lang| {
+            let ctx = &ctx;
+            async move {
+                let res = match options.combined {
+                    true => collect_lang(ctx, lang, options, sampled_at).await,
+                    false => process_lang(ctx, lang, dst, options, sampled_at)
+                        .await
+                        .map(|sample| (vec![], sample)),
+                };
+                let language = lang.file_name().to_string_lossy().to_string();
+                if let Err(e) = &res {
+                    eprintln!("Error sampling {}: {}", language, e);
+                }
+                (language, res)
+            }
+        })
+        .buffered(options.concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut manifest = Manifest::new(options, sampled_at);
+    let mut samples = vec![];
+    for (language, res) in results {
+        match res {
+            Ok((batches, sample)) => {
+                if !batches.is_empty() {
+                    samples.push(ctx.read_batches(batches)?);
+                }
+                manifest.languages.insert(language, sample);
+            }
+            Err(e) => {
+                manifest.failed.insert(language, e.to_string());
+            }
+        }
+    }
+
+    if options.combined && !samples.is_empty() {
+        let dst = dst.join(format!("{}.{}", COMBINED_FILE, options.format.extension()));
+        let metadata = ("filter_policy", json_string(&options.policy)?);
+        output::write(
+            union_by_name(&ctx, samples)?,
+            &dst,
+            options.format,
+            Some(metadata),
+        )
+        .await?;
+    }
+    manifest.write(&dst.join(MANIFEST_FILE))?;
+
+    if !manifest.failed.is_empty() {
+        return Err(DataFusionError::Execution(format!(
+            "{} of {} languages failed",
+            manifest.failed.len(),
+            folder_paths.len()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{corpus, doc, strings, temp_dir, write_parquet, Doc};
+
+    fn options() -> SamplerOptions {
+        SamplerOptions {
+            seed: 3,
+            languages: vec![],
+            exclude_languages: vec![],
+            policy: FilterPolicy::default(),
+            sampling: Sampling::Uniform { size: 5 },
+            multilingual: None,
+            hosts: HostFilter::default(),
+            concurrency: 4,
+            target_partitions: 2,
+            memory_limit: None,
+            combined: true,
+            format: OutputFormat::Parquet,
+        }
+    }
+
+    /// Languages and record ids of the rows of the combined file, in order
+    async fn combined_rows(src: &Path, dst: &Path) -> Vec<(Option<String>, Option<String>)> {
+        sample(src, dst, &options()).await.unwrap();
+        let path = dst.join("sample.parquet");
+        let df = SessionContext::new()
+            .read_parquet(path.to_str().unwrap(), ParquetReadOptions::default())
+            .await
+            .unwrap();
+        strings(df.clone(), "sample_lang")
+            .await
+            .into_iter()
+            .zip(strings(df, "warc_record_id").await)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn combined_output_only_depends_on_the_seed() {
+        let dir = temp_dir("combined-order");
+        let src = dir.join("src");
+        // languages of different sizes, which finish sampling in varying orders
+        for (lang, n) in [("de", 400), ("fr", 3), ("it", 40), ("nl", 1)] {
+            let docs: Vec<Doc> = (0..n)
+                .map(|i| Doc {
+                    categories: Some(vec!["news"]),
+                    ..doc(&format!("<urn:uuid:{}-{}>", lang, i))
+                })
+                .collect();
+            write_parquet(corpus(&docs), &src.join(lang).join("part_1.parquet")).await;
+        }
+
+        let rows = combined_rows(&src, &dir.join("first")).await;
+        let languages: Vec<&str> = rows
+            .iter()
+            .filter_map(|(lang, _)| lang.as_deref())
+            .collect();
+        assert_eq!(languages.len(), 14);
+        assert!(languages.is_sorted(), "{:?}", languages);
+        assert_eq!(combined_rows(&src, &dir.join("second")).await, rows);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
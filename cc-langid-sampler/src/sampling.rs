@@ -0,0 +1,250 @@
+use datafusion::error::Result;
+use datafusion::prelude::*;
+use serde::Serialize;
+
+use crate::signals;
+
+/// Number of documents sampled per language by default
+pub const SAMPLE_SIZE: usize = 1000;
+
+/// How the rows of a language are picked once filtered
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "mode")]
+pub enum Sampling {
+    /// `size` rows picked uniformly
+    Uniform { size: usize },
+    /// `per_bin` rows picked uniformly in each `identified_doc_prob` bin. Bins are delimited by
+    /// increasing `edges`, the last one includes its upper edge
+    ProbBins { edges: BinEdges, per_bin: usize },
+    /// `size` rows, picking first the documents whose language signals agree the least and
+    /// then uniformly among the ones that agree as much
+    Disagreement { size: usize },
+}
+
+/// Increasing edges of `identified_doc_prob` bins
+pub type BinEdges = Vec<f32>;
+
+/// Parses `deciles` or comma separated increasing bin edges like `0,0.5,0.8,1`
+pub fn parse_bins(bins: &str) -> Result<BinEdges, String> {
+    if bins == "deciles" {
+        return Ok((0..=10).map(|i| i as f32 / 10.0).collect());
+    }
+    let edges = bins
+        .split(',')
+        .map(|edge| edge.trim().parse::<f32>().map_err(|e| e.to_string()))
+        .collect::<Result<Vec<f32>, String>>()?;
+    if edges.len() < 2 {
+        return Err("at least two bin edges are needed".to_string());
+    }
+    if edges.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err("bin edges must be increasing".to_string());
+    }
+    Ok(edges)
+}
+
+/// Key a row is sampled by: a hash of the seed and the WARC record id. Rows without a record
+/// id are hashed by their content instead, so that they are still sampled and don't all share
+/// the key of an empty id. Taking the rows with the smallest keys gives a uniform sample of
+/// exactly `n` rows (or every row if there are fewer), which only depends on the seed and not
+/// on the order files are read in.
+fn sample_key(seed: u64) -> Expr {
+    md5(concat(vec![
+        lit(format!("{}:", seed)),
+        coalesce(vec![col("warc_record_id"), col("content")]),
+    ]))
+}
+
+/// Order rows are sampled in: by [`sample_key`], then by record id and content to break ties
+pub fn sample_order(seed: u64) -> Vec<Expr> {
+    vec![
+        sample_key(seed).sort(true, true),
+        col("warc_record_id").sort(true, true),
+        col("content").sort(true, true),
+    ]
+}
+
+/// Picks `n` rows uniformly, after ordering them by `first`
+fn take_by(df: DataFrame, first: Vec<Expr>, seed: u64, n: usize) -> Result<DataFrame> {
+    let mut order = first;
+    order.extend(sample_order(seed));
+    df.sort(order)?.limit(0, Some(n))
+}
+
+/// Picks `n` rows uniformly
+fn take(df: DataFrame, seed: u64, n: usize) -> Result<DataFrame> {
+    take_by(df, vec![], seed, n)
+}
+
+impl Sampling {
+    /// Picks the rows of a language, adding a `prob_bin` column with the bin of each row when
+    /// stratifying, and the language signal columns when looking for disagreements
+    pub fn apply(&self, df: DataFrame, seed: u64) -> Result<DataFrame> {
+        match self {
+            Sampling::Uniform { size } => take(df, seed, *size),
+            Sampling::ProbBins { edges, per_bin } => {
+                let mut sample: Option<DataFrame> = None;
+                let last = edges.len() - 2;
+                for (i, bin) in edges.windows(2).enumerate() {
+                    let (low, high) = (bin[0], bin[1]);
+                    let prob = col("identified_doc_prob");
+                    let (upper, label) = match i == last {
+                        true => (
+                            prob.clone().lt_eq(lit(high)),
+                            format!("[{}, {}]", low, high),
+                        ),
+                        false => (prob.clone().lt(lit(high)), format!("[{}, {})", low, high)),
+                    };
+                    let bin = take(
+                        df.clone().filter(prob.gt_eq(lit(low)).and(upper))?,
+                        seed,
+                        *per_bin,
+                    )?
+                    .with_column("prob_bin", lit(label))?;
+                    sample = Some(match sample {
+                        Some(sample) => sample.union(bin)?,
+                        None => bin,
+                    });
+                }
+                Ok(sample.expect("at least two bin edges"))
+            }
+            Sampling::Disagreement { size } => {
+                let df = signals::with_signals(df)?;
+                let agreement = col("language_agreement").sort(true, false);
+                take_by(df, vec![agreement], seed, *size)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::common::ScalarValue;
+
+    use super::*;
+    use crate::fixtures::{corpus, doc, ids, strings, Doc};
+
+    fn docs(n: usize) -> Vec<Doc> {
+        (0..n).map(|i| doc(&format!("<urn:uuid:{}>", i))).collect()
+    }
+
+    async fn uniform(docs: &[Doc], size: usize, seed: u64) -> Vec<String> {
+        let sampling = Sampling::Uniform { size };
+        ids(sampling.apply(corpus(docs), seed).unwrap()).await
+    }
+
+    #[tokio::test]
+    async fn uniform_takes_exactly_size_rows() {
+        let sample = uniform(&docs(50), 10, 1).await;
+        assert_eq!(sample.len(), 10);
+        let mut distinct = sample.clone();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn uniform_takes_every_row_when_there_are_fewer() {
+        assert_eq!(uniform(&docs(5), 10, 1).await.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn uniform_only_depends_on_the_seed() {
+        let docs = docs(50);
+        let sample = uniform(&docs, 10, 7).await;
+        assert_eq!(uniform(&docs, 10, 7).await, sample);
+
+        let reversed: Vec<_> = docs.iter().rev().cloned().collect();
+        assert_eq!(uniform(&reversed, 10, 7).await, sample);
+
+        assert_ne!(uniform(&docs, 10, 8).await, sample);
+    }
+
+    #[tokio::test]
+    async fn rows_without_record_id_are_keyed_by_content() {
+        let docs = docs(50);
+        let without_ids = |docs: &[Doc]| {
+            corpus(docs)
+                .with_column("warc_record_id", lit(ScalarValue::Utf8(None)))
+                .unwrap()
+        };
+        let contents = |docs: &[Doc], seed: u64| {
+            let sample = take(without_ids(docs), seed, 10).unwrap();
+            async move { strings(sample, "content").await }
+        };
+        let sample = contents(&docs, 7).await;
+        assert_eq!(sample.len(), 10);
+
+        let reversed: Vec<_> = docs.iter().rev().cloned().collect();
+        assert_eq!(contents(&reversed, 7).await, sample);
+        assert_ne!(contents(&docs, 8).await, sample);
+    }
+
+    #[test]
+    fn bins_are_deciles_or_increasing_edges() {
+        let deciles = parse_bins("deciles").unwrap();
+        assert_eq!(deciles.len(), 11);
+        assert_eq!((deciles[0], deciles[10]), (0.0, 1.0));
+        assert_eq!(parse_bins("0, 0.5,1").unwrap(), vec![0.0, 0.5, 1.0]);
+
+        assert!(parse_bins("0.5").is_err());
+        assert!(parse_bins("0,0.5,0.5").is_err());
+        assert!(parse_bins("0,1,0.5").is_err());
+        assert!(parse_bins("0,half,1").is_err());
+    }
+
+    #[tokio::test]
+    async fn prob_bins_take_per_bin_rows_and_label_them() {
+        let probs = [0.1, 0.2, 0.3, 0.6, 1.0];
+        let docs: Vec<Doc> = probs
+            .iter()
+            .enumerate()
+            .map(|(i, prob)| Doc {
+                prob: *prob,
+                ..doc(&i.to_string())
+            })
+            .collect();
+        let sampling = Sampling::ProbBins {
+            edges: vec![0.0, 0.5, 1.0],
+            per_bin: 2,
+        };
+        let sample = sampling.apply(corpus(&docs), 1).unwrap();
+        let mut rows: Vec<(String, String)> = ids(sample.clone())
+            .await
+            .into_iter()
+            .zip(strings(sample, "prob_bin").await.into_iter().flatten())
+            .collect();
+        rows.sort();
+
+        assert_eq!(rows.len(), 4);
+        assert!(rows[..2]
+            .iter()
+            .all(|(id, bin)| ["0", "1", "2"].contains(&id.as_str()) && bin == "[0, 0.5)"));
+        // the last bin includes its upper edge
+        assert_eq!(
+            rows[2..],
+            [
+                ("3".to_string(), "[0.5, 1]".to_string()),
+                ("4".to_string(), "[0.5, 1]".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn disagreement_takes_the_least_agreeing_documents_first() {
+        let signals = |id: &str, lang: &str, cld2: &'static str, line: &'static str| Doc {
+            lang: lang.to_string(),
+            cld2_langs: Some(vec![cld2]),
+            line_langs: Some(vec![line]),
+            ..doc(id)
+        };
+        let docs = vec![
+            signals("agree", "fr", "fra", "fr"),
+            signals("unknown", "fr", "", ""),
+            signals("two", "fr", "eng", "fr"),
+            signals("three", "fr", "eng", "de"),
+        ];
+        let sampling = Sampling::Disagreement { size: 3 };
+        let sample = sampling.apply(corpus(&docs), 1).unwrap();
+        assert_eq!(ids(sample).await, vec!["three", "two", "agree"]);
+    }
+}
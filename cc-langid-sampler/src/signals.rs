@@ -0,0 +1,201 @@
+use datafusion::arrow::array::{Array, ArrayRef, AsArray, Float64Array, StringArray};
+use datafusion::arrow::datatypes::DataType;
+use datafusion::error::Result;
+use datafusion::logical_expr::{ColumnarValue, ScalarUDF, Volatility};
+use datafusion::prelude::*;
+use isolang::Language;
+use std::{collections::BTreeMap, str::FromStr, sync::Arc};
+
+use crate::multilingual::{line_shares, list_type};
+
+/// Columns holding the normalized language of each signal, in the order they are compared
+const SIGNAL_COLUMNS: [&str; 3] = ["doc_lang", "cld2_lang", "line_lang"];
+
+/// Normalizes a language label to its ISO 639-3 code: `__label__fr`, `fr`, `fra` and `fr-FR`
+/// all become `fra`. Unknown codes are kept lowercased.
+pub fn normalize_lang(label: &str) -> Option<String> {
+    let label = label.trim().trim_start_matches("__label__").to_lowercase();
+    let code = label.split(['_', '-']).next().unwrap_or_default();
+    if code.is_empty() {
+        return None;
+    }
+    Some(match Language::from_str(code) {
+        Ok(language) => language.to_639_3().to_string(),
+        Err(_) => code.to_string(),
+    })
+}
+
+/// Share of the available signals agreeing with the most common label: 1 when they all agree,
+/// 1/3 when the three signals differ. Not set when fewer than two signals are available.
+fn agreement(labels: &[Option<&str>]) -> Option<f64> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for label in labels.iter().flatten() {
+        *counts.entry(label).or_default() += 1;
+    }
+    let available: usize = counts.values().sum();
+    if available < 2 {
+        return None;
+    }
+    let majority = counts.values().max().copied().unwrap_or_default();
+    Some(majority as f64 / available as f64)
+}
+
+fn string_udf(
+    name: &str,
+    input_types: Vec<DataType>,
+    fun: impl Fn(&[ArrayRef]) -> StringArray + Send + Sync + 'static,
+) -> ScalarUDF {
+    create_udf(
+        name,
+        input_types,
+        Arc::new(DataType::Utf8),
+        Volatility::Immutable,
+        Arc::new(move |args: &[ColumnarValue]| {
+            let args = ColumnarValue::values_to_arrays(args)?;
+            Ok(ColumnarValue::Array(Arc::new(fun(&args)) as ArrayRef))
+        }),
+    )
+}
+
+/// Adds the normalized label of each language signal and their agreement:
+/// - `doc_lang`: OSCAR's document language, `identified_doc_lang`
+/// - `cld2_lang`: the first language CLD2 identified in Common Crawl,
+///   `warc_identified_content_language`
+/// - `line_lang`: the most common language of the lines, from `sentence_langs`
+/// - `language_agreement`: share of the signals agreeing with the most common label
+pub fn with_signals(df: DataFrame) -> Result<DataFrame> {
+    let normalize = string_udf("normalize_lang", vec![DataType::Utf8], |args| {
+        args[0]
+            .as_string::<i32>()
+            .iter()
+            .map(|label| label.and_then(normalize_lang))
+            .collect()
+    });
+    let first_lang = string_udf("first_lang", vec![list_type(DataType::Utf8)], |args| {
+        let labels = args[0].as_list::<i32>();
+        (0..labels.len())
+            .map(|row| {
+                if labels.is_null(row) {
+                    return None;
+                }
+                let labels = labels.value(row);
+                let labels = labels.as_string::<i32>();
+                labels.iter().flatten().find_map(normalize_lang)
+            })
+            .collect()
+    });
+    let line_lang = string_udf(
+        "line_lang",
+        vec![list_type(DataType::Utf8), list_type(DataType::Float32)],
+        |args| {
+            let (langs, probs) = (args[0].as_list::<i32>(), args[1].as_list::<i32>());
+            (0..langs.len())
+                .map(|row| {
+                    line_shares(langs, probs, row, 0.0)
+                        .into_iter()
+                        .next()
+                        .map(|(lang, _)| lang)
+                })
+                .collect()
+        },
+    );
+    let agreement = create_udf(
+        "language_agreement",
+        vec![DataType::Utf8; SIGNAL_COLUMNS.len()],
+        Arc::new(DataType::Float64),
+        Volatility::Immutable,
+        Arc::new(|args: &[ColumnarValue]| {
+            let args = ColumnarValue::values_to_arrays(args)?;
+            let signals: Vec<&StringArray> =
+                args.iter().map(|arg| arg.as_string::<i32>()).collect();
+            let agreements: Float64Array = (0..signals[0].len())
+                .map(|row| {
+                    let labels: Vec<Option<&str>> = signals
+                        .iter()
+                        .map(|signal| signal.is_valid(row).then(|| signal.value(row)))
+                        .collect();
+                    agreement(&labels)
+                })
+                .collect();
+            Ok(ColumnarValue::Array(Arc::new(agreements) as ArrayRef))
+        }),
+    );
+
+    df.with_column("doc_lang", normalize.call(vec![col("identified_doc_lang")]))?
+        .with_column(
+            "cld2_lang",
+            first_lang.call(vec![col("warc_identified_content_language")]),
+        )?
+        .with_column(
+            "line_lang",
+            line_lang.call(vec![col("sentence_langs"), col("sentences_probs")]),
+        )?
+        .with_column(
+            "language_agreement",
+            agreement.call(SIGNAL_COLUMNS.iter().map(|c| col(*c)).collect()),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{corpus, doc, strings, Doc};
+    use datafusion::arrow::datatypes::Float64Type;
+
+    #[test]
+    fn labels_are_normalized_to_iso_639_3() {
+        for label in ["__label__fr", "fr", "fra", "fr-FR", " FR_fr "] {
+            assert_eq!(normalize_lang(label).as_deref(), Some("fra"), "{}", label);
+        }
+        assert_eq!(normalize_lang("xx-YY").as_deref(), Some("xx"));
+        assert_eq!(normalize_lang("__label__"), None);
+        assert_eq!(normalize_lang(""), None);
+    }
+
+    #[test]
+    fn agreement_is_the_majority_share() {
+        assert_eq!(
+            agreement(&[Some("fra"), Some("fra"), Some("fra")]),
+            Some(1.0)
+        );
+        assert_eq!(
+            agreement(&[Some("fra"), Some("eng"), Some("fra")]),
+            Some(2.0 / 3.0)
+        );
+        assert_eq!(
+            agreement(&[Some("fra"), Some("eng"), Some("deu")]),
+            Some(1.0 / 3.0)
+        );
+        assert_eq!(agreement(&[Some("fra"), None, Some("eng")]), Some(0.5));
+        assert_eq!(agreement(&[Some("fra"), None, None]), None);
+    }
+
+    #[tokio::test]
+    async fn signals_are_added_as_columns() {
+        let docs = vec![Doc {
+            lang: "hi".to_string(),
+            cld2_langs: Some(vec!["hin", "eng"]),
+            line_langs: Some(vec!["en", "hi", "en"]),
+            ..doc("mixed")
+        }];
+        let df = with_signals(corpus(&docs)).unwrap();
+        for (column, lang) in [
+            ("doc_lang", "hin"),
+            ("cld2_lang", "hin"),
+            ("line_lang", "eng"),
+        ] {
+            assert_eq!(
+                strings(df.clone(), column).await,
+                vec![Some(lang.to_string())]
+            );
+        }
+        let batches = df
+            .select_columns(&["language_agreement"])
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let agreement = batches[0].column(0).as_primitive::<Float64Type>().value(0);
+        assert!((agreement - 2.0 / 3.0).abs() < 1e-9);
+    }
+}
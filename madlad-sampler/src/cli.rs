@@ -1,17 +1,206 @@
-use clap::Parser;
+use chrono::NaiveDate;
+use clap::{Parser, ValueEnum, builder::RangedU64ValueParser};
+use serde::Serialize;
 use std::path::PathBuf;
 
-#[derive(Parser)]
+use madlad_sampler::{
+    dedup::DedupScope, output::OutputLayout, pii::PiiCategory, sources::madlad::DataVersion,
+};
+
+use crate::logging::{LogFormat, LogLevel};
+
+/// Parses a `--pii-language` override, `LANG=CATEGORY,...` or `LANG=none`
+fn parse_pii_language(value: &str) -> Result<(String, Vec<PiiCategory>), String> {
+    let (language, categories) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Expected LANG=CATEGORIES, got {value}"))?;
+    if categories == "none" {
+        return Ok((language.to_string(), vec![]));
+    }
+    let categories = categories
+        .split(',')
+        .map(|category| PiiCategory::from_str(category, true))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((language.to_string(), categories))
+}
+
+/// Parses a share between 0 and 1
+fn parse_share(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(share) if (0.0..=1.0).contains(&share) => Ok(share),
+        _ => Err(format!("Expected a number between 0 and 1, got {value}")),
+    }
+}
+
+/// Corpus the input folder holds
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    /// MADLAD-400 language folders of gzipped jsonl shards
+    #[default]
+    Madlad,
+    /// OSCAR language folders of parquet files produced by `oscar2parquet`. Documents with
+    /// quality warnings are skipped
+    Oscar,
+    /// Common Crawl WET files, split into languages by their identified content language
+    Wet,
+}
+
+#[derive(Parser, Serialize)]
 #[command(name = "madlad-sampler")]
 #[command(author = "Pedro Ortiz Suarez <pedro@commoncrawl.org>")]
 #[command(version = "0.1.0")]
-#[command(about = "Sample documents from MADLAD", long_about = None)]
+#[command(about = "Sample documents from MADLAD, OSCAR or Common Crawl WET files", long_about = None)]
 pub struct Args {
-    /// Folder containing the data
+    /// Folder containing the language folders, or the MADLAD root containing the `data` and
+    /// `data-v1p5` folders when `--data-version` is given
     #[arg(value_name = "INPUT FOLDER")]
     pub src: PathBuf,
 
-    /// Parquet file to write
-    #[arg(value_name = "DESTINATION FILE")]
-    pub dst: PathBuf,
+    /// Parquet file to write, or the folder to write to with `--layout per-language|hive`
+    #[arg(value_name = "DESTINATION", required_unless_present = "dry_run")]
+    pub dst: Option<PathBuf>,
+
+    /// List the languages, files and sizes of the input and estimate their number of documents
+    /// instead of sampling. Only the first documents of the smallest clean and noisy file of each
+    /// language are read
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Count the documents of every file in `--dry-run` mode instead of estimating them
+    #[arg(long, requires = "dry_run")]
+    pub exact_counts: bool,
+
+    /// Also write the `--dry-run` inventory to this JSON file
+    #[arg(long, value_name = "JSON FILE", requires = "dry_run")]
+    pub inventory_file: Option<PathBuf>,
+
+    /// Corpus to sample from
+    #[arg(long, value_enum, default_value = "madlad")]
+    pub source: SourceKind,
+
+    /// Release of the corpus written to the `version` column for OSCAR and WET inputs.
+    /// MADLAD releases are read from the folder names
+    #[arg(long, value_name = "VERSION", default_value = "unknown")]
+    pub corpus_version: String,
+
+    /// Seed used to shuffle the order in which the shards of a language are read.
+    /// Shards are read in file name order when no seed is given
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Maximum number of documents per registered domain in each language sample.
+    /// Documents from domains that reached the cap are skipped and sampling continues
+    #[arg(long, value_name = "K")]
+    pub max_per_domain: Option<usize>,
+
+    /// Dynabench task config (e.g. `assets/config.yml`). Only languages whose ISO 639-3 code
+    /// is one of the task `back_label`s are sampled. With `--task-languages`, only used to
+    /// resolve the task `display_label`s of the list to their `back_label`
+    #[arg(long, value_name = "CONFIG FILE")]
+    pub task_config: Option<PathBuf>,
+
+    /// File with one language code or English language name per line (e.g.
+    /// `assets/languages.txt`). Only the listed languages are sampled. Names resolve through
+    /// the `display_label`s of `--task-config` or else through the ISO 639 names. Entries that
+    /// don't resolve to a language code fail the run
+    #[arg(long, value_name = "LANGUAGES FILE")]
+    pub task_languages: Option<PathBuf>,
+
+    /// Skip documents with fewer characters. Applied to the snippet in snippet mode
+    #[arg(long, value_name = "N")]
+    pub min_chars: Option<usize>,
+
+    /// Skip documents with more characters. Applied to the snippet in snippet mode
+    #[arg(long, value_name = "N")]
+    pub max_chars: Option<usize>,
+
+    /// Skip documents with fewer whitespace separated words. Applied to the snippet in snippet mode
+    #[arg(long, value_name = "N")]
+    pub min_words: Option<usize>,
+
+    /// Skip documents with more whitespace separated words. Applied to the snippet in snippet mode
+    #[arg(long, value_name = "N")]
+    pub max_words: Option<usize>,
+
+    /// Replace each document by a window of N consecutive lines taken at a random position.
+    /// The offsets of the window are written to `snippet_start`/`snippet_end`
+    #[arg(long, value_name = "N", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub snippet_lines: Option<usize>,
+
+    /// MADLAD release(s) to sample from. The input folder is then expected to be the MADLAD root.
+    /// Without it, the input folder holds the language folders of a single release
+    #[arg(long, value_enum)]
+    pub data_version: Option<DataVersion>,
+
+    /// Glob matched against the file names of the clean shards of a language folder
+    #[arg(long, value_name = "GLOB", default_value = "clean_*")]
+    pub clean_glob: String,
+
+    /// Glob matched against the file names of the noisy shards of a language folder
+    #[arg(long, value_name = "GLOB", default_value = "noisy_*")]
+    pub noisy_glob: String,
+
+    /// Only sample documents crawled on or after this date (YYYY-MM-DD). Documents without a
+    /// parseable timestamp are skipped when a date bound is set
+    #[arg(long, value_name = "DATE")]
+    pub from: Option<NaiveDate>,
+
+    /// Only sample documents crawled on or before this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub to: Option<NaiveDate>,
+
+    /// Split each language sample evenly across the crawl years between `--from` and `--to`
+    #[arg(long, requires_all = ["from", "to"])]
+    pub stratify_by_year: bool,
+
+    /// Replace emails, IBANs, payment card numbers, IP addresses and phone numbers in the sampled
+    /// text by placeholders such as `[EMAIL]`. The count is written to `pii_redactions`
+    #[arg(long)]
+    pub redact_pii: bool,
+
+    /// Categories of personal data to redact
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "email,iban,card,ip,phone",
+        requires = "redact_pii"
+    )]
+    pub pii_categories: Vec<PiiCategory>,
+
+    /// Categories to redact for one language instead of `--pii-categories`, e.g. `deu=email,iban`
+    /// or `hi_Latn=none`. The language is a folder name or an ISO 639-3 code. Can be repeated
+    #[arg(long, value_name = "LANG=CATEGORIES", value_parser = parse_pii_language, requires = "redact_pii")]
+    pub pii_language: Vec<(String, Vec<PiiCategory>)>,
+
+    /// Skip documents whose share of letters outside of the script of their language is above
+    /// this threshold, between 0 and 1. The script is the one in the language folder name
+    /// (`hi_Latn`), or else the usual script of the language. Languages whose script is not
+    /// known are not checked. The dominant script of every document is written to
+    /// `detected_script`
+    #[arg(long, value_name = "SHARE", value_parser = parse_share)]
+    pub script_mismatch_threshold: Option<f64>,
+
+    /// Most verbose log messages written to stderr
+    #[arg(long, value_enum, default_value = "info")]
+    pub log_level: LogLevel,
+
+    /// Write log messages as text or as JSON lines
+    #[arg(long, value_enum, default_value = "text")]
+    pub log_format: LogFormat,
+
+    /// Write a single file, one file per language, or Hive partitions (`lang=xxx/`). Every file
+    /// is written under a temporary name and renamed once complete
+    #[arg(long, value_enum, default_value = "single")]
+    pub layout: OutputLayout,
+
+    /// Target number of rows per parquet row group
+    #[arg(long, value_name = "N", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub rows_per_row_group: Option<usize>,
+
+    /// Drop documents whose normalized text was already sampled, within each language or across
+    /// the whole sample. Dropped documents are replaced by the next ones read
+    #[arg(long, value_enum, default_value = "none")]
+    pub dedup: DedupScope,
 }
@@ -0,0 +1,191 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+
+use crate::errors::MadError;
+
+/// Compression of a shard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    None,
+}
+
+impl Compression {
+    /// Detects the compression from the magic bytes at the start of the file, so that shards
+    /// with a wrong or missing extension are still decoded. Falls back to the file extension
+    /// when the magic bytes don't identify a compression, e.g. for an empty file.
+    pub fn detect(path: &Path) -> Result<Self, MadError> {
+        let mut magic = [0; 4];
+        let mut file = File::open(path)?;
+        let read = file.read(&mut magic)?;
+        Ok(Self::from_magic(&magic[..read])
+            .or_else(|| Self::from_extension(path))
+            .unwrap_or(Compression::None))
+    }
+
+    fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("gz" | "gzip") => Some(Compression::Gzip),
+            Some("zst" | "zstd") => Some(Compression::Zstd),
+            Some("bz2" | "bzip2") => Some(Compression::Bzip2),
+            _ => None,
+        }
+    }
+
+    fn from_magic(magic: &[u8]) -> Option<Self> {
+        match magic {
+            [0x1f, 0x8b, ..] => Some(Compression::Gzip),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Compression::Zstd),
+            [b'B', b'Z', b'h', ..] => Some(Compression::Bzip2),
+            _ => None,
+        }
+    }
+}
+
+/// Counts the bytes read from a file, before decompression
+struct CountingReader<R> {
+    inner: R,
+    read: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.read.fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+/// Opens a shard, transparently decompressing it
+pub fn open_shard(path: &Path) -> Result<Box<dyn BufRead>, MadError> {
+    open_counted(path, Arc::default())
+}
+
+/// Opens a shard like [`open_shard`], adding the compressed bytes read from it to `read`
+pub fn open_counted(path: &Path, read: Arc<AtomicU64>) -> Result<Box<dyn BufRead>, MadError> {
+    let compression = Compression::detect(path)?;
+    let file = CountingReader {
+        inner: File::open(path)?,
+        read,
+    };
+    let reader: Box<dyn Read> = match compression {
+        Compression::Gzip => Box::new(MultiGzDecoder::new(file)),
+        Compression::Zstd => Box::new(zstd::Decoder::new(file)?),
+        Compression::Bzip2 => Box::new(MultiBzDecoder::new(file)),
+        Compression::None => Box::new(file),
+    };
+    Ok(Box::new(BufReader::new(reader)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write, path::PathBuf};
+
+    use bzip2::write::BzEncoder;
+    use flate2::write::GzEncoder;
+
+    use super::*;
+    use crate::fixtures::temp_dir;
+
+    const LINES: &str = "{\"text\": \"Hallo\"}\n{\"text\": \"Welt\"}\n";
+
+    /// Writes `LINES` compressed with `compression` to `name` in `dir`
+    fn write_shard(dir: &Path, name: &str, compression: Compression) -> PathBuf {
+        let bytes = match compression {
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(LINES.as_bytes()).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::Zstd => zstd::encode_all(LINES.as_bytes(), 0).unwrap(),
+            Compression::Bzip2 => {
+                let mut encoder = BzEncoder::new(vec![], bzip2::Compression::default());
+                encoder.write_all(LINES.as_bytes()).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::None => LINES.as_bytes().to_vec(),
+        };
+        let path = dir.join(name);
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn read_lines(path: &Path) -> Vec<String> {
+        open_shard(path)
+            .unwrap()
+            .lines()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn shards_round_trip() {
+        let dir = temp_dir("compression-round-trip");
+        for (name, compression) in [
+            ("clean_0.jsonl.gz", Compression::Gzip),
+            ("clean_1.jsonl.zst", Compression::Zstd),
+            ("clean_2.jsonl.bz2", Compression::Bzip2),
+            ("clean_3.jsonl", Compression::None),
+        ] {
+            let path = write_shard(&dir, name, compression);
+            assert_eq!(Compression::detect(&path).unwrap(), compression, "{name}");
+            assert_eq!(
+                read_lines(&path),
+                LINES.lines().collect::<Vec<_>>(),
+                "{name}"
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn detects_by_extension_without_magic_bytes() {
+        let dir = temp_dir("compression-extension");
+        for (name, compression) in [
+            ("empty.gz", Compression::Gzip),
+            ("empty.ZST", Compression::Zstd),
+            ("empty.bzip2", Compression::Bzip2),
+            ("empty.jsonl", Compression::None),
+        ] {
+            fs::write(dir.join(name), "").unwrap();
+            assert_eq!(Compression::detect(&dir.join(name)).unwrap(), compression);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn detects_by_magic_bytes_with_a_wrong_or_missing_extension() {
+        let dir = temp_dir("compression-magic");
+        for (name, compression) in [
+            ("clean_0", Compression::Gzip),
+            ("clean_1.jsonl", Compression::Zstd),
+            ("clean_2.gz", Compression::Bzip2),
+            ("clean_3.data", Compression::None),
+        ] {
+            let path = write_shard(&dir, name, compression);
+            assert_eq!(Compression::detect(&path).unwrap(), compression, "{name}");
+            assert_eq!(
+                read_lines(&path),
+                LINES.lines().collect::<Vec<_>>(),
+                "{name}"
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Utc};
+use serde::Serialize;
+
+use crate::schemas::Document;
+
+/// Formats of timestamps without an offset, read as UTC
+const NAIVE_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+
+/// Parses a crawl timestamp: RFC 3339 (`2019-03-18T19:39:53Z`), a date and time without offset
+/// read as UTC, or a bare date (`2019-03-18`)
+pub fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    let raw = raw.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(raw) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+    for format in NAIVE_FORMATS {
+        if let Ok(timestamp) = NaiveDateTime::parse_from_str(raw, format) {
+            return Some(timestamp.and_utc());
+        }
+    }
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .map(|date| date.and_time(Default::default()).and_utc())
+}
+
+/// Splits the timestamp of a source document into the parsed value and, if it couldn't be
+/// parsed, the original string
+pub fn source_timestamp(raw: Option<String>) -> (Option<DateTime<Utc>>, Option<String>) {
+    match raw {
+        None => (None, None),
+        Some(raw) => match parse_timestamp(&raw) {
+            Some(timestamp) => (Some(timestamp), None),
+            None => (None, Some(raw)),
+        },
+    }
+}
+
+/// Inclusive range of crawl dates documents must fall in
+#[derive(Debug, Default, Clone)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub fn is_set(&self) -> bool {
+        self.from.is_some() || self.to.is_some()
+    }
+
+    /// Returns whether a timestamp is in the range. Documents without a timestamp are only
+    /// accepted when no bound is set.
+    pub fn contains(&self, timestamp: Option<DateTime<Utc>>) -> bool {
+        let Some(timestamp) = timestamp else {
+            return !self.is_set();
+        };
+        let date = timestamp.date_naive();
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+
+    /// Years covered by the range, if both bounds are set
+    pub fn years(&self) -> Option<Vec<i32>> {
+        Some((self.from?.year()..=self.to?.year()).collect())
+    }
+}
+
+/// Timestamps of the documents read for a language sample
+#[derive(Debug, Default, Serialize)]
+pub struct TimestampStats {
+    /// Documents read with a parseable timestamp
+    pub parsed: usize,
+    /// Documents read without a timestamp
+    pub missing: usize,
+    /// Documents read whose timestamp couldn't be parsed
+    pub unparseable: usize,
+    /// Documents skipped because they were outside of the date range
+    pub out_of_range: usize,
+    /// Number of sampled documents per crawl year
+    pub sampled_years: BTreeMap<i32, usize>,
+}
+
+impl TimestampStats {
+    /// Counts the timestamp of a document read from a shard
+    pub fn count(&mut self, doc: &Document) {
+        match (doc.timestamp, &doc.unparsed_timestamp) {
+            (Some(_), _) => self.parsed += 1,
+            (None, Some(_)) => self.unparseable += 1,
+            (None, None) => self.missing += 1,
+        }
+    }
+
+    /// Counts the crawl years of the sampled documents
+    pub fn count_sampled(&mut self, records: &[Document]) {
+        for timestamp in records.iter().filter_map(|doc| doc.timestamp) {
+            *self.sampled_years.entry(timestamp.year()).or_default() += 1;
+        }
+    }
+}
+
+/// Equal per-year quotas of a language sample, used to balance it across crawl years
+#[derive(Debug)]
+pub struct YearQuota {
+    per_year: usize,
+    counts: BTreeMap<i32, usize>,
+}
+
+impl YearQuota {
+    /// Splits `limit` documents evenly across `years`, rounding up
+    pub fn new(years: &[i32], limit: usize) -> Self {
+        YearQuota {
+            per_year: limit.div_ceil(years.len().max(1)),
+            counts: years.iter().map(|year| (*year, 0)).collect(),
+        }
+    }
+
+    /// Returns whether the year of a document still has room in the sample
+    pub fn has_room(&self, doc: &Document) -> bool {
+        doc.timestamp
+            .and_then(|timestamp| self.counts.get(&timestamp.year()))
+            .is_some_and(|count| *count < self.per_year)
+    }
+
+    /// Counts a document that entered the sample
+    pub fn insert(&mut self, doc: &Document) {
+        if let Some(count) = doc
+            .timestamp
+            .and_then(|timestamp| self.counts.get_mut(&timestamp.year()))
+        {
+            *count += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::fixtures::document;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, s).unwrap()
+    }
+
+    fn date(raw: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(raw, "%Y-%m-%d").unwrap()
+    }
+
+    fn dated(raw: &str) -> Document {
+        Document {
+            timestamp: parse_timestamp(raw),
+            ..document("Hallo")
+        }
+    }
+
+    #[test]
+    fn timestamp_formats() {
+        let expected = utc(2019, 3, 18, 19, 39, 53);
+        assert_eq!(parse_timestamp("2019-03-18T19:39:53Z"), Some(expected));
+        assert_eq!(parse_timestamp("2019-03-18T21:39:53+02:00"), Some(expected));
+        assert_eq!(parse_timestamp(" 2019-03-18T19:39:53 "), Some(expected));
+        assert_eq!(parse_timestamp("2019-03-18 19:39:53"), Some(expected));
+        assert_eq!(
+            parse_timestamp("2019-03-18T19:39:53.250"),
+            Some(expected + chrono::Duration::milliseconds(250))
+        );
+        assert_eq!(
+            parse_timestamp("2019-03-18"),
+            Some(utc(2019, 3, 18, 0, 0, 0))
+        );
+    }
+
+    #[test]
+    fn unparseable_timestamps() {
+        for raw in [
+            "",
+            "yesterday",
+            "18/03/2019",
+            "2019-13-01",
+            "2019-03-18T25:00:00Z",
+        ] {
+            assert_eq!(parse_timestamp(raw), None, "{raw}");
+        }
+        assert_eq!(
+            source_timestamp(Some("yesterday".to_string())),
+            (None, Some("yesterday".to_string()))
+        );
+        assert_eq!(source_timestamp(None), (None, None));
+    }
+
+    #[test]
+    fn date_range_bounds_are_inclusive() {
+        let range = DateRange {
+            from: Some(date("2019-03-01")),
+            to: Some(date("2019-03-31")),
+        };
+        assert!(range.contains(Some(utc(2019, 3, 1, 0, 0, 0))));
+        assert!(range.contains(Some(utc(2019, 3, 31, 23, 59, 59))));
+        assert!(!range.contains(Some(utc(2019, 2, 28, 23, 59, 59))));
+        assert!(!range.contains(Some(utc(2019, 4, 1, 0, 0, 0))));
+        assert_eq!(range.years(), Some(vec![2019]));
+    }
+
+    #[test]
+    fn open_date_ranges() {
+        let from = DateRange {
+            from: Some(date("2019-03-01")),
+            to: None,
+        };
+        assert!(from.contains(Some(utc(2030, 1, 1, 0, 0, 0))));
+        assert!(!from.contains(Some(utc(2019, 2, 1, 0, 0, 0))));
+        assert_eq!(from.years(), None);
+
+        let to = DateRange {
+            from: None,
+            to: Some(date("2019-03-01")),
+        };
+        assert!(to.contains(Some(utc(2001, 1, 1, 0, 0, 0))));
+        assert!(!to.contains(Some(utc(2019, 3, 2, 0, 0, 0))));
+    }
+
+    #[test]
+    fn documents_without_timestamp_need_an_unbounded_range() {
+        assert!(DateRange::default().contains(None));
+        let range = DateRange {
+            from: Some(date("2019-03-01")),
+            to: None,
+        };
+        assert!(!range.contains(None));
+    }
+
+    #[test]
+    fn year_quota_stops_at_the_per_year_limit() {
+        // 5 documents over 2 years round up to 3 per year
+        let mut quota = YearQuota::new(&[2019, 2020], 5);
+        let doc_2019 = dated("2019-06-01");
+        for _ in 0..3 {
+            assert!(quota.has_room(&doc_2019));
+            quota.insert(&doc_2019);
+        }
+        assert!(!quota.has_room(&doc_2019));
+        assert!(quota.has_room(&dated("2020-06-01")));
+
+        // Documents outside of the quota years or without a timestamp never have room
+        assert!(!quota.has_room(&dated("2021-06-01")));
+        assert!(!quota.has_room(&document("Hallo")));
+    }
+}
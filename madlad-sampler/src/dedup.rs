@@ -0,0 +1,206 @@
+use std::{
+    collections::HashSet,
+    hash::{BuildHasher, BuildHasherDefault, DefaultHasher},
+};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::schemas::Document;
+
+/// Which documents a new document is compared against
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupScope {
+    /// Keep duplicates
+    #[default]
+    None,
+    /// Drop documents whose text was already sampled for the same language
+    Language,
+    /// Drop documents whose text was already sampled for any language
+    Global,
+}
+
+/// Hash of the text with case, punctuation and whitespace differences removed
+pub fn text_fingerprint(text: &str) -> u64 {
+    let normalized = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<String>>()
+        .join(" ");
+    BuildHasherDefault::<DefaultHasher>::default().hash_one(normalized)
+}
+
+fn url_fingerprint(url: &str) -> u64 {
+    BuildHasherDefault::<DefaultHasher>::default().hash_one(("url", url))
+}
+
+/// What a document is matched on: its whole text, computed before snippet extraction, and its
+/// URL when matching by URL
+#[derive(Debug, Clone, Copy)]
+pub struct Fingerprint {
+    text: u64,
+    url: Option<u64>,
+}
+
+/// Duplicates dropped from a language sample
+#[derive(Debug, Default, Serialize)]
+pub struct DedupStats {
+    /// Documents whose text or URL had already been sampled for the same language
+    pub language_duplicates: usize,
+    /// Documents whose text had already been sampled for another language
+    pub global_duplicates: usize,
+    /// Share of the documents offered to the sample that were dropped as duplicates
+    pub duplicate_rate: f64,
+}
+
+/// Drops documents that were already sampled, so that the sample is filled with fresh ones
+pub struct Deduplicator {
+    scope: DedupScope,
+    /// Whether documents are also matched by URL, used when a language spans several releases
+    by_url: bool,
+    language: HashSet<u64>,
+    global: HashSet<u64>,
+    stats: DedupStats,
+    kept: usize,
+}
+
+impl Deduplicator {
+    pub fn new(scope: DedupScope) -> Self {
+        Deduplicator {
+            scope,
+            by_url: false,
+            language: HashSet::new(),
+            global: HashSet::new(),
+            stats: DedupStats::default(),
+            kept: 0,
+        }
+    }
+
+    /// Starts deduplicating a new language. Documents are also matched by URL when `by_url` is
+    /// set, so that the releases of a language are always deduplicated against each other.
+    pub fn start_language(&mut self, by_url: bool) {
+        self.by_url = by_url;
+        self.language.clear();
+        self.stats = DedupStats::default();
+        self.kept = 0;
+    }
+
+    fn enabled(&self) -> bool {
+        self.by_url || self.scope != DedupScope::None
+    }
+
+    /// Fingerprint of a document, taken before its text is cut to a snippet so that documents
+    /// are compared whole. `None` when nothing is deduplicated.
+    pub fn fingerprint(&self, doc: &Document) -> Option<Fingerprint> {
+        if !self.enabled() {
+            return None;
+        }
+        Some(Fingerprint {
+            text: text_fingerprint(&doc.text),
+            url: doc
+                .url
+                .as_deref()
+                .filter(|_| self.by_url)
+                .map(url_fingerprint),
+        })
+    }
+
+    /// Returns whether the document with this fingerprint is a duplicate, counting it if so
+    pub fn is_duplicate(&mut self, fingerprint: &Fingerprint) -> bool {
+        let Fingerprint { text, url } = *fingerprint;
+        if self.language.contains(&text) || url.is_some_and(|u| self.language.contains(&u)) {
+            self.stats.language_duplicates += 1;
+            return true;
+        }
+        if self.scope == DedupScope::Global && self.global.contains(&text) {
+            self.stats.global_duplicates += 1;
+            return true;
+        }
+        false
+    }
+
+    /// Records the fingerprint of a document that entered the sample
+    pub fn insert(&mut self, fingerprint: &Fingerprint) {
+        self.language.insert(fingerprint.text);
+        if let Some(url) = fingerprint.url {
+            self.language.insert(url);
+        }
+        if self.scope == DedupScope::Global {
+            self.global.insert(fingerprint.text);
+        }
+        self.kept += 1;
+    }
+
+    /// Duplicate counts of the current language, or `None` if nothing was deduplicated
+    pub fn finish_language(&mut self) -> Option<DedupStats> {
+        if !self.enabled() {
+            return None;
+        }
+        let mut stats = std::mem::take(&mut self.stats);
+        let dropped = stats.language_duplicates + stats.global_duplicates;
+        if dropped + self.kept > 0 {
+            stats.duplicate_rate = dropped as f64 / (dropped + self.kept) as f64;
+        }
+        Some(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::document;
+
+    /// Offers a document to the deduplicator, like the sampler does before cutting its text to a
+    /// snippet, and returns whether it was kept
+    fn offer(dedup: &mut Deduplicator, text: &str) -> bool {
+        let fingerprint = dedup.fingerprint(&document(text)).unwrap();
+        if dedup.is_duplicate(&fingerprint) {
+            return false;
+        }
+        dedup.insert(&fingerprint);
+        true
+    }
+
+    #[test]
+    fn fingerprints_ignore_case_and_punctuation() {
+        assert_eq!(
+            text_fingerprint("Hallo, Welt!"),
+            text_fingerprint("hallo   welt")
+        );
+        assert_ne!(
+            text_fingerprint("Hallo Welt"),
+            text_fingerprint("Hallo Wald")
+        );
+    }
+
+    #[test]
+    fn documents_sharing_a_snippet_are_kept() {
+        let mut dedup = Deduplicator::new(DedupScope::Language);
+        dedup.start_language(false);
+        // Both documents would be cut to the same `Impressum` snippet
+        assert!(offer(&mut dedup, "Impressum\nErster Artikel"));
+        assert!(offer(&mut dedup, "Impressum\nZweiter Artikel"));
+        assert!(!offer(&mut dedup, "Impressum\nErster Artikel"));
+
+        let stats = dedup.finish_language().unwrap();
+        assert_eq!(stats.language_duplicates, 1);
+    }
+
+    #[test]
+    fn global_scope_spans_languages() {
+        let mut dedup = Deduplicator::new(DedupScope::Global);
+        dedup.start_language(false);
+        assert!(offer(&mut dedup, "Hallo Welt"));
+        dedup.start_language(false);
+        assert!(!offer(&mut dedup, "Hallo Welt"));
+        assert_eq!(dedup.finish_language().unwrap().global_duplicates, 1);
+    }
+
+    #[test]
+    fn nothing_is_fingerprinted_without_a_scope() {
+        let dedup = Deduplicator::new(DedupScope::None);
+        assert!(dedup.fingerprint(&document("Hallo")).is_none());
+    }
+}
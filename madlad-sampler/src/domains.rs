@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use url::{Host, Url};
+
+/// Returns the registered domain (eTLD+1) of a document URL, e.g. `https://en.m.wikipedia.org/x`
+/// gives `wikipedia.org`. Falls back to the full host when it has no known public suffix or is
+/// an IP address.
+pub fn registered_domain(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    if let Host::Ipv4(_) | Host::Ipv6(_) = url.host()? {
+        return url.host_str().map(str::to_string);
+    }
+    let host = url.host_str()?.trim_end_matches('.').to_lowercase();
+    match psl::domain_str(&host) {
+        Some(domain) => Some(domain.to_string()),
+        None => Some(host),
+    }
+}
+
+/// Counts sampled documents per registered domain and enforces an optional per-domain cap.
+#[derive(Debug, Default)]
+pub struct DomainCap {
+    max_per_domain: Option<usize>,
+    counts: HashMap<String, usize>,
+    rejected: usize,
+}
+
+impl DomainCap {
+    pub fn new(max_per_domain: Option<usize>) -> Self {
+        DomainCap {
+            max_per_domain,
+            ..Default::default()
+        }
+    }
+
+    /// Returns whether a document with this URL may enter the sample, and counts it if so.
+    /// Documents without a parseable URL are always admitted and not counted.
+    pub fn admit(&mut self, url: Option<&str>) -> bool {
+        let Some(domain) = url.and_then(registered_domain) else {
+            return true;
+        };
+        let count = self.counts.entry(domain).or_default();
+        if self.max_per_domain.is_some_and(|max| *count >= max) {
+            self.rejected += 1;
+            return false;
+        }
+        *count += 1;
+        true
+    }
+
+    pub fn diversity(&self) -> DomainDiversity {
+        let total: usize = self.counts.values().sum();
+        let top = self
+            .counts
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)));
+        DomainDiversity {
+            distinct_domains: self.counts.values().filter(|c| **c > 0).count(),
+            top_domain: top.map(|(domain, _)| domain.clone()),
+            top_domain_share: match (top, total) {
+                (Some((_, count)), total) if total > 0 => *count as f64 / total as f64,
+                _ => 0.0,
+            },
+            capped_documents: self.rejected,
+        }
+    }
+}
+
+/// Domain diversity of a language sample, reported in the manifest
+#[derive(Debug, Serialize)]
+pub struct DomainDiversity {
+    pub distinct_domains: usize,
+    pub top_domain: Option<String>,
+    pub top_domain_share: f64,
+    /// Documents skipped because their domain had reached the cap
+    pub capped_documents: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registered_domains() {
+        assert_eq!(
+            registered_domain("https://en.m.wikipedia.org/x").as_deref(),
+            Some("wikipedia.org")
+        );
+        assert_eq!(
+            registered_domain("http://news.bbc.co.uk/").as_deref(),
+            Some("bbc.co.uk")
+        );
+        assert_eq!(
+            registered_domain("http://192.168.0.1/").as_deref(),
+            Some("192.168.0.1")
+        );
+        assert_eq!(
+            registered_domain("http://10.0.0.1/a").as_deref(),
+            Some("10.0.0.1")
+        );
+        assert_eq!(
+            registered_domain("http://[2001:db8::1]/").as_deref(),
+            Some("[2001:db8::1]")
+        );
+        assert_eq!(registered_domain("not a url"), None);
+    }
+}
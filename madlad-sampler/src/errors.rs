@@ -5,6 +5,9 @@ use core::fmt;
 pub enum MadError {
     ParseLanguageError(isolang::ParseLanguageError),
     Custom(String),
+    Io(std::io::Error),
+    Parquet(parquet::errors::ParquetError),
+    Arrow(arrow::error::ArrowError),
     SerdeJson(serde_json::Error),
 }
 
@@ -14,6 +17,9 @@ impl fmt::Display for MadError {
             MadError::ParseLanguageError(ref err) => err.fmt(f),
             MadError::SerdeJson(ref err) => err.fmt(f),
             MadError::Custom(ref err) => err.fmt(f),
+            MadError::Io(ref err) => err.fmt(f),
+            MadError::Parquet(ref err) => err.fmt(f),
+            MadError::Arrow(ref err) => err.fmt(f),
         }
     }
 }
@@ -30,6 +36,24 @@ impl From<serde_json::Error> for MadError {
     }
 }
 
+impl From<std::io::Error> for MadError {
+    fn from(err: std::io::Error) -> Self {
+        MadError::Io(err)
+    }
+}
+
+impl From<parquet::errors::ParquetError> for MadError {
+    fn from(err: parquet::errors::ParquetError) -> Self {
+        MadError::Parquet(err)
+    }
+}
+
+impl From<arrow::error::ArrowError> for MadError {
+    fn from(err: arrow::error::ArrowError) -> Self {
+        MadError::Arrow(err)
+    }
+}
+
 impl From<String> for MadError {
     fn from(err: String) -> Self {
         MadError::Custom(err)
@@ -0,0 +1,191 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::schemas::Document;
+
+/// A contiguous window of lines taken from a document
+#[derive(Debug, PartialEq)]
+pub struct Snippet {
+    pub text: String,
+    /// Character offset of the first character of the window in the original text
+    pub start: usize,
+    /// Character offset one past the last character of the window in the original text
+    pub end: usize,
+}
+
+/// Extracts `lines` consecutive lines starting at a random line, among the windows holding at
+/// least one non-blank line. Documents that have no more than `lines` lines are returned whole.
+pub fn extract_snippet<R: Rng>(text: &str, lines: usize, rng: &mut R) -> Snippet {
+    // Character offset at which every line starts
+    let mut starts = vec![0];
+    let mut chars = 0;
+    for c in text.chars() {
+        chars += 1;
+        if c == '\n' {
+            starts.push(chars);
+        }
+    }
+    // A final newline ends the last line and doesn't start an empty one
+    let trailing_newline = text.ends_with('\n');
+    if trailing_newline {
+        starts.pop();
+    }
+
+    if starts.len() <= lines {
+        return Snippet {
+            text: text.to_string(),
+            start: 0,
+            end: chars,
+        };
+    }
+
+    let blank: Vec<bool> = text
+        .split('\n')
+        .take(starts.len())
+        .map(|line| line.trim().is_empty())
+        .collect();
+    let windows: Vec<usize> = (0..=starts.len() - lines)
+        .filter(|first| blank[*first..first + lines].contains(&false))
+        .collect();
+    let first = match windows.is_empty() {
+        true => rng.random_range(0..=starts.len() - lines),
+        false => windows[rng.random_range(0..windows.len())],
+    };
+    let start = starts[first];
+    // Drop the newline that ends the last line of the window
+    let end = match starts.get(first + lines) {
+        Some(next) => next - 1,
+        None if trailing_newline => chars - 1,
+        None => chars,
+    };
+    Snippet {
+        text: text.chars().skip(start).take(end - start).collect(),
+        start,
+        end,
+    }
+}
+
+/// Length bounds and snippet size applied to every document before it enters a sample
+#[derive(Debug, Default, Clone)]
+pub struct TextFilterOptions {
+    pub min_chars: Option<usize>,
+    pub max_chars: Option<usize>,
+    pub min_words: Option<usize>,
+    pub max_words: Option<usize>,
+    pub snippet_lines: Option<usize>,
+}
+
+/// Length filters and snippet extraction applied to every document before it enters a sample
+pub struct TextFilter {
+    options: TextFilterOptions,
+    rng: StdRng,
+}
+
+impl TextFilter {
+    pub fn new(options: TextFilterOptions, seed: Option<u64>) -> Self {
+        TextFilter {
+            options,
+            rng: match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_os_rng(),
+            },
+        }
+    }
+
+    fn accepts(&self, text: &str) -> bool {
+        let chars = text.chars().count();
+        let words = text.split_whitespace().count();
+        let options = &self.options;
+        options.min_chars.is_none_or(|min| chars >= min)
+            && options.max_chars.is_none_or(|max| chars <= max)
+            && options.min_words.is_none_or(|min| words >= min)
+            && options.max_words.is_none_or(|max| words <= max)
+    }
+
+    /// Replaces the text of the document by a snippet if snippets are enabled, and returns
+    /// whether the resulting text passes the length filters.
+    pub fn apply(&mut self, doc: &mut Document) -> bool {
+        if let Some(lines) = self.options.snippet_lines {
+            let snippet = extract_snippet(&doc.text, lines, &mut self.rng);
+            doc.text = snippet.text;
+            doc.snippet_start = Some(snippet.start as u64);
+            doc.snippet_end = Some(snippet.end as u64);
+        }
+        self.accepts(&doc.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Snippets of every seed in a small range
+    fn snippets(text: &str, lines: usize) -> Vec<Snippet> {
+        (0..32)
+            .map(|seed| extract_snippet(text, lines, &mut StdRng::seed_from_u64(seed)))
+            .collect()
+    }
+
+    #[test]
+    fn short_documents_are_kept_whole() {
+        let snippet = extract_snippet("a\nb\n", 2, &mut StdRng::seed_from_u64(0));
+        assert_eq!(
+            snippet,
+            Snippet {
+                text: "a\nb\n".to_string(),
+                start: 0,
+                end: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn snippets_hold_the_requested_lines() {
+        for text in ["a\nb\nc\nd", "a\nb\nc\nd\n"] {
+            let mut windows: Vec<String> = snippets(text, 2)
+                .into_iter()
+                .map(|snippet| snippet.text)
+                .collect();
+            windows.sort();
+            windows.dedup();
+            assert_eq!(windows, ["a\nb", "b\nc", "c\nd"], "{text:?}");
+        }
+    }
+
+    #[test]
+    fn snippets_are_not_blank() {
+        for text in ["a\n\n\n\nb", "a\n \n\t\n\nb\n"] {
+            let mut windows: Vec<String> = snippets(text, 1)
+                .into_iter()
+                .map(|snippet| snippet.text)
+                .collect();
+            windows.sort();
+            windows.dedup();
+            assert_eq!(windows, ["a", "b"], "{text:?}");
+        }
+        let windows: Vec<String> = snippets("a\n\n\n\nb", 2)
+            .into_iter()
+            .map(|snippet| snippet.text)
+            .collect();
+        assert!(
+            windows
+                .iter()
+                .all(|window| ["a\n", "\nb"].contains(&window.as_str()))
+        );
+    }
+
+    #[test]
+    fn offsets_are_in_characters() {
+        let text = "été\nüber\nstraße\nнет\n";
+        for snippet in snippets(text, 2) {
+            let window: String = text
+                .chars()
+                .skip(snippet.start)
+                .take(snippet.end - snippet.start)
+                .collect();
+            assert_eq!(window, snippet.text);
+            assert_eq!(snippet.text.lines().count(), 2);
+        }
+        let starts: Vec<usize> = snippets(text, 2).iter().map(|s| s.start).collect();
+        assert!(starts.iter().all(|start| [0, 4, 9].contains(start)));
+    }
+}
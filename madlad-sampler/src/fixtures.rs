@@ -0,0 +1,65 @@
+//! Documents and files shared by the unit tests
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use flate2::write::GzEncoder;
+
+use crate::schemas::Document;
+
+/// A clean German MADLAD document holding `text`
+pub fn document(text: &str) -> Document {
+    Document {
+        text: text.to_string(),
+        lang: "deu".to_string(),
+        script: None,
+        locale: None,
+        timestamp: None,
+        unparsed_timestamp: None,
+        url: None,
+        clean: true,
+        source: "MADLAD".to_string(),
+        version: "v1".to_string(),
+        source_file: "de/clean_docs_0.jsonl.gz".to_string(),
+        source_line: 1,
+        snippet_start: None,
+        snippet_end: None,
+        pii_redactions: None,
+        detected_script: None,
+    }
+}
+
+/// An empty folder in the system temporary folder, unique to a test of this process
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("madlad-sampler-{}-{}", name, std::process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes `contents` to `path`, gzipped when its extension is `gz`, creating its folder
+pub fn write_file(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let bytes = match path.extension().is_some_and(|e| e == "gz") {
+        true => {
+            let mut encoder = GzEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(contents.as_bytes()).unwrap();
+            encoder.finish().unwrap()
+        }
+        false => contents.as_bytes().to_vec(),
+    };
+    fs::write(path, bytes).unwrap();
+}
+
+/// MADLAD jsonl lines of documents holding the given texts
+pub fn madlad_lines<'a>(texts: impl IntoIterator<Item = &'a str>) -> String {
+    texts
+        .into_iter()
+        .map(|text| format!("{}\n", serde_json::json!({ "text": text })))
+        .collect()
+}
@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+
+use log::warn;
+use serde::Serialize;
+
+use crate::{
+    errors::MadError,
+    sources::{CorpusSource, Shard, SourceLanguage},
+};
+
+/// Files of one kind (clean or noisy) of a language
+#[derive(Debug, Default, Serialize)]
+pub struct ShardCounts {
+    pub files: usize,
+    /// Compressed size in bytes
+    pub bytes: u64,
+    /// Number of documents, counted or estimated. Not set for unparseable languages, or when a
+    /// file couldn't be read
+    pub documents: Option<u64>,
+    /// Whether `documents` was counted over every file rather than estimated
+    pub exact: bool,
+}
+
+/// What was found for a language name of the corpus
+#[derive(Debug, Serialize)]
+pub struct LanguageInventory {
+    pub name: String,
+    /// ISO 639-3 code, if the name could be parsed
+    pub tag: Option<String>,
+    pub script: Option<String>,
+    pub locale: Option<String>,
+    /// Why the name couldn't be parsed. These languages are skipped when sampling
+    pub error: Option<String>,
+    /// Why the files of the language couldn't be listed or counted. Sampling skips these files
+    pub count_error: Option<String>,
+    pub clean: ShardCounts,
+    pub noisy: ShardCounts,
+}
+
+/// Languages and files of a corpus, listed without sampling it
+#[derive(Debug, Serialize)]
+pub struct Inventory {
+    pub source: String,
+    pub languages: Vec<LanguageInventory>,
+    /// Number of languages per script, `none` when the name has no script
+    pub scripts: BTreeMap<String, usize>,
+    /// Number of languages per locale, `none` when the name has no locale
+    pub locales: BTreeMap<String, usize>,
+}
+
+/// Counts the documents of a shard, including the ones that fail to decode
+fn count_documents(
+    source: &dyn CorpusSource,
+    lang: &SourceLanguage,
+    shard: &Shard,
+) -> Result<u64, MadError> {
+    let documents = source
+        .documents(lang, shard)
+        .map_err(|e| MadError::Custom(format!("{}: {}", shard.path.display(), e)))?;
+    Ok(documents.count() as u64)
+}
+
+/// Documents read from the smallest file of a language to estimate its number of documents
+const ESTIMATE_DOCUMENTS: usize = 1000;
+
+/// Estimates the documents of `shards` from the first `limit` documents of the smallest one,
+/// extrapolated from the compressed bytes they span to the compressed size of every file. The
+/// smallest file is read in full when it holds fewer documents, or when its source doesn't count
+/// the bytes it reads.
+fn estimate_documents(
+    source: &dyn CorpusSource,
+    lang: &SourceLanguage,
+    shards: &[&Shard],
+    limit: usize,
+) -> Result<u64, MadError> {
+    let Some(smallest) = shards.iter().min_by_key(|shard| shard.size) else {
+        return Ok(0);
+    };
+    let mut documents = source
+        .documents(lang, smallest)
+        .map_err(|e| MadError::Custom(format!("{}: {}", smallest.path.display(), e)))?;
+    let mut read = documents.by_ref().take(limit).count() as u64;
+    let bytes: u64 = shards.iter().map(|shard| shard.size).sum();
+    let (read, read_bytes) = match documents.next() {
+        Some(_) if smallest.read_bytes() > 0 => (read + 1, smallest.read_bytes()),
+        Some(_) => {
+            read += 1 + documents.count() as u64;
+            (read, smallest.size)
+        }
+        None => (read, smallest.size),
+    };
+    Ok(match read_bytes {
+        0 => read * shards.len() as u64,
+        read_bytes => (read as f64 * bytes as f64 / read_bytes as f64).round() as u64,
+    })
+}
+
+/// Counts or estimates the documents of the files of one kind of a language. Without `exact`,
+/// only the start of the smallest file is read, see [`estimate_documents`].
+fn shard_documents(
+    source: &dyn CorpusSource,
+    lang: &SourceLanguage,
+    shards: &[&Shard],
+    exact: bool,
+) -> Result<u64, MadError> {
+    if !exact {
+        return estimate_documents(source, lang, shards, ESTIMATE_DOCUMENTS);
+    }
+    let mut documents = 0;
+    for shard in shards {
+        documents += count_documents(source, lang, shard)?;
+    }
+    Ok(documents)
+}
+
+/// Counts the files of one kind of a language. When a file can't be read, the number of
+/// documents is not set and the error is returned with the counts.
+fn count_shards(
+    source: &dyn CorpusSource,
+    lang: &SourceLanguage,
+    shards: &[&Shard],
+    exact: bool,
+) -> (ShardCounts, Option<MadError>) {
+    let mut counts = ShardCounts {
+        files: shards.len(),
+        bytes: shards.iter().map(|shard| shard.size).sum(),
+        documents: None,
+        exact,
+    };
+    match shard_documents(source, lang, shards, exact) {
+        Ok(documents) => {
+            counts.documents = Some(documents);
+            (counts, None)
+        }
+        Err(e) => (counts, Some(e)),
+    }
+}
+
+/// Lists the languages of `source`, their files and sizes, and counts or estimates their
+/// number of documents
+pub fn inventory(source: &dyn CorpusSource, exact: bool) -> Result<Inventory, MadError> {
+    let mut inventory = Inventory {
+        source: source.name().to_string(),
+        languages: vec![],
+        scripts: BTreeMap::new(),
+        locales: BTreeMap::new(),
+    };
+
+    for (name, paths) in source.language_names()? {
+        let lang = match SourceLanguage::parse(&name, paths) {
+            Ok(lang) => lang,
+            Err(e) => {
+                inventory.languages.push(LanguageInventory {
+                    name,
+                    tag: None,
+                    script: None,
+                    locale: None,
+                    error: Some(e.to_string()),
+                    count_error: None,
+                    clean: ShardCounts::default(),
+                    noisy: ShardCounts::default(),
+                });
+                continue;
+            }
+        };
+
+        let (shards, shards_error) = match source.shards(&lang) {
+            Ok(shards) => (shards, None),
+            Err(e) => (vec![], Some(e)),
+        };
+        let (clean, noisy): (Vec<&Shard>, Vec<&Shard>) =
+            shards.iter().partition(|shard| shard.clean);
+
+        let none = || "none".to_string();
+        *inventory
+            .scripts
+            .entry(lang.script.clone().unwrap_or_else(none))
+            .or_default() += 1;
+        *inventory
+            .locales
+            .entry(lang.locale.clone().unwrap_or_else(none))
+            .or_default() += 1;
+
+        let (clean, clean_error) = count_shards(source, &lang, &clean, exact);
+        let (noisy, noisy_error) = count_shards(source, &lang, &noisy, exact);
+        let count_error = shards_error.or(clean_error).or(noisy_error);
+        if let Some(e) = &count_error {
+            warn!("Error counting the documents of {}: {}", name, e);
+        }
+        inventory.languages.push(LanguageInventory {
+            clean,
+            noisy,
+            name,
+            tag: Some(lang.tag),
+            script: lang.script,
+            locale: lang.locale,
+            error: None,
+            count_error: count_error.map(|e| e.to_string()),
+        });
+    }
+    Ok(inventory)
+}
+
+fn format_documents(counts: &ShardCounts) -> String {
+    match (counts.documents, counts.exact) {
+        (None, _) => "-".to_string(),
+        (Some(documents), true) => documents.to_string(),
+        (Some(documents), false) => format!("~{documents}"),
+    }
+}
+
+impl Inventory {
+    /// Prints the inventory as a table, followed by the script and locale breakdowns
+    pub fn print(&self) {
+        println!(
+            "{:<16} {:<5} {:<6} {:<6} {:>6} {:>14} {:>12} {:>6} {:>14} {:>12}",
+            "language",
+            "code",
+            "script",
+            "locale",
+            "clean",
+            "clean bytes",
+            "clean docs",
+            "noisy",
+            "noisy bytes",
+            "noisy docs"
+        );
+        for lang in &self.languages {
+            if let Some(error) = &lang.error {
+                println!("{:<16} unparseable: {}", lang.name, error);
+                continue;
+            }
+            println!(
+                "{:<16} {:<5} {:<6} {:<6} {:>6} {:>14} {:>12} {:>6} {:>14} {:>12}",
+                lang.name,
+                lang.tag.as_deref().unwrap_or("-"),
+                lang.script.as_deref().unwrap_or("-"),
+                lang.locale.as_deref().unwrap_or("-"),
+                lang.clean.files,
+                lang.clean.bytes,
+                format_documents(&lang.clean),
+                lang.noisy.files,
+                lang.noisy.bytes,
+                format_documents(&lang.noisy),
+            );
+            if let Some(error) = &lang.count_error {
+                println!("{:<16} unreadable: {}", "", error);
+            }
+        }
+
+        let unparseable = self.languages.iter().filter(|l| l.error.is_some()).count();
+        let unreadable = self
+            .languages
+            .iter()
+            .filter(|l| l.count_error.is_some())
+            .count();
+        println!(
+            "{} languages found in {}, {} unparseable, {} with unreadable files",
+            self.languages.len(),
+            self.source,
+            unparseable,
+            unreadable
+        );
+        for (title, breakdown) in [("Scripts", &self.scripts), ("Locales", &self.locales)] {
+            let counts: Vec<String> = breakdown
+                .iter()
+                .map(|(key, count)| format!("{key}: {count}"))
+                .collect();
+            println!("{}: {}", title, counts.join(", "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        fixtures::{madlad_lines, temp_dir, write_file},
+        sources::madlad::MadladSource,
+    };
+
+    fn language<'a>(inventory: &'a Inventory, name: &str) -> &'a LanguageInventory {
+        inventory.languages.iter().find(|l| l.name == name).unwrap()
+    }
+
+    #[test]
+    fn madlad_tree() {
+        let src = temp_dir("inventory-tree");
+        write_file(
+            &src.join("de/clean_docs_0.jsonl.gz"),
+            &madlad_lines(["Eins", "Zwei", "Drei"]),
+        );
+        write_file(
+            &src.join("de/clean_docs_1.jsonl"),
+            &madlad_lines(["Vier", "Fünf"]),
+        );
+        write_file(
+            &src.join("de/noisy_docs_0.jsonl.gz"),
+            &madlad_lines(["a", "b", "c", "d"]),
+        );
+        write_file(
+            &src.join("hi_Latn/clean_docs_0.jsonl.gz"),
+            &madlad_lines(["Namaste"]),
+        );
+        fs::create_dir(src.join("12345")).unwrap();
+        let source = MadladSource::new(&src, None, "clean_*", "noisy_*").unwrap();
+
+        let exact = inventory(&source, true).unwrap();
+        assert_eq!(
+            exact
+                .languages
+                .iter()
+                .map(|l| l.name.as_str())
+                .collect::<Vec<_>>(),
+            ["12345", "de", "hi_Latn"]
+        );
+        let de = language(&exact, "de");
+        assert_eq!(de.tag.as_deref(), Some("deu"));
+        assert_eq!((de.clean.files, de.noisy.files), (2, 1));
+        let size = |name: &str| src.join("de").join(name).metadata().unwrap().len();
+        assert_eq!(
+            de.clean.bytes,
+            size("clean_docs_0.jsonl.gz") + size("clean_docs_1.jsonl")
+        );
+        assert_eq!(de.noisy.bytes, size("noisy_docs_0.jsonl.gz"));
+        assert_eq!((de.clean.documents, de.noisy.documents), (Some(5), Some(4)));
+        assert!(de.clean.exact);
+
+        let hi = language(&exact, "hi_Latn");
+        assert_eq!(
+            (
+                hi.tag.as_deref(),
+                hi.script.as_deref(),
+                hi.locale.as_deref()
+            ),
+            (Some("hin"), Some("Latn"), None)
+        );
+        assert_eq!((hi.clean.files, hi.noisy.files), (1, 0));
+        assert!(language(&exact, "12345").error.is_some());
+        assert_eq!(exact.scripts.get("Latn"), Some(&1));
+        assert_eq!(exact.scripts.get("none"), Some(&1));
+
+        // The estimate reads the single file of a kind in full, so it agrees with the count
+        let estimated = inventory(&source, false).unwrap();
+        let (de_estimate, hi_estimate) =
+            (language(&estimated, "de"), language(&estimated, "hi_Latn"));
+        assert!(!de_estimate.noisy.exact);
+        assert_eq!(de_estimate.noisy.documents, de.noisy.documents);
+        assert_eq!(hi_estimate.clean.documents, hi.clean.documents);
+        assert_eq!(hi_estimate.noisy.documents, Some(0));
+        fs::remove_dir_all(&src).unwrap();
+    }
+
+    #[test]
+    fn estimates_read_the_start_of_the_smallest_file() {
+        let src = temp_dir("inventory-estimate");
+        let text = "x".repeat(100);
+        let lines = madlad_lines((0..5000).map(|_| text.as_str()));
+        write_file(&src.join("de/clean_docs_0.jsonl"), &lines);
+        write_file(&src.join("de/clean_docs_1.jsonl"), &lines);
+        let source = MadladSource::new(&src, None, "clean_*", "noisy_*").unwrap();
+        let lang = &source.languages().unwrap()[0];
+        let shards = source.shards(lang).unwrap();
+        let shards: Vec<&Shard> = shards.iter().collect();
+
+        let estimate = estimate_documents(&source, lang, &shards, 1000).unwrap();
+        assert!(shards[0].read_bytes() < shards[0].size / 2);
+        assert!(estimate.abs_diff(10_000) < 1000, "{estimate}");
+        fs::remove_dir_all(&src).unwrap();
+    }
+}
@@ -1,2 +1,17 @@
+pub mod compression;
+pub mod dates;
+pub mod dedup;
+pub mod domains;
 pub mod errors;
+pub mod filters;
+#[cfg(test)]
+mod fixtures;
+pub mod inventory;
+pub mod output;
+pub mod pii;
+pub mod progress;
+pub mod sampler;
 pub mod schemas;
+pub mod scripts;
+pub mod sources;
+pub mod tasks;
@@ -0,0 +1,97 @@
+use std::io::Write;
+
+use clap::ValueEnum;
+use env_logger::Builder;
+use log::{LevelFilter, Record};
+use serde::Serialize;
+
+/// Most verbose messages logged
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    /// Also logs every shard read and every document that couldn't be decoded
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+/// How log records are written to stderr
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    /// One JSON object per line with `timestamp`, `level`, `target` and `message`
+    Json,
+}
+
+/// A log record as written in the `json` format
+fn json_record(timestamp: &str, record: &Record) -> serde_json::Value {
+    serde_json::json!({
+        "timestamp": timestamp,
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    })
+}
+
+/// Sets up the logger. `RUST_LOG` can still override the level of single modules
+pub fn init(level: LogLevel, format: LogFormat) {
+    let mut builder = Builder::new();
+    builder.filter_level(level.into()).parse_default_env();
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let line = json_record(&buf.timestamp_millis().to_string(), record);
+            writeln!(buf, "{line}")
+        });
+    }
+    builder.init();
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use super::*;
+
+    #[test]
+    fn levels() {
+        assert_eq!(LevelFilter::from(LogLevel::Error), LevelFilter::Error);
+        assert_eq!(LevelFilter::from(LogLevel::Info), LevelFilter::Info);
+        assert_eq!(LevelFilter::from(LogLevel::Trace), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn json_records() {
+        let line = json_record(
+            "2025-01-01T00:00:00.000Z",
+            &Record::builder()
+                .level(Level::Warn)
+                .target("madlad_sampler::sampler")
+                .args(format_args!("Skipped {} shards", 2))
+                .build(),
+        );
+        assert_eq!(
+            line,
+            serde_json::json!({
+                "timestamp": "2025-01-01T00:00:00.000Z",
+                "level": "WARN",
+                "target": "madlad_sampler::sampler",
+                "message": "Skipped 2 shards",
+            })
+        );
+    }
+}
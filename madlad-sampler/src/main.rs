@@ -1,15 +1,122 @@
+use std::fs::File;
+
 use clap::Parser;
+use log::error;
+
+use madlad_sampler::{
+    dates::DateRange,
+    errors::MadError,
+    filters::TextFilterOptions,
+    inventory::inventory,
+    output::OutputOptions,
+    pii::PiiRules,
+    sampler::{Sampler, SamplerOptions},
+    sources::{CorpusSource, madlad::MadladSource, oscar::OscarSource, wet::WetSource},
+    tasks::TaskLanguages,
+};
+
+use crate::{
+    cli::{Args, SourceKind},
+    manifest::Manifest,
+};
 
 mod cli;
-mod sampler;
+mod logging;
+mod manifest;
+
+// The test fixtures of the library, which refer to its modules from the crate root
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "fixtures.rs"]
+mod fixtures;
+#[cfg(test)]
+use madlad_sampler::schemas;
+
+fn run(args: &Args) -> Result<(), MadError> {
+    let source: Box<dyn CorpusSource> = match args.source {
+        SourceKind::Madlad => Box::new(MadladSource::new(
+            &args.src,
+            args.data_version,
+            &args.clean_glob,
+            &args.noisy_glob,
+        )?),
+        SourceKind::Oscar => Box::new(OscarSource::new(&args.src, &args.corpus_version)),
+        SourceKind::Wet => Box::new(WetSource::new(&args.src, &args.corpus_version)),
+    };
+
+    if args.dry_run {
+        let inventory = inventory(source.as_ref(), args.exact_counts)?;
+        inventory.print();
+        if let Some(path) = &args.inventory_file {
+            serde_json::to_writer_pretty(File::create(path)?, &inventory)?;
+        }
+        return Ok(());
+    }
+    let Some(dst) = &args.dst else {
+        return Err("A destination is required when sampling".to_string().into());
+    };
+
+    if let (Some(from), Some(to)) = (args.from, args.to)
+        && from > to
+    {
+        return Err(format!("--from {from} is after --to {to}").into());
+    }
+
+    let task = match (&args.task_config, &args.task_languages) {
+        (config, Some(list)) => Some(TaskLanguages::from_list(list, config.as_deref())?),
+        (Some(config), None) => Some(TaskLanguages::from_config(config)?),
+        (None, None) => None,
+    };
+    let options = SamplerOptions {
+        seed: args.seed,
+        max_per_domain: args.max_per_domain,
+        dedup: args.dedup,
+        text: TextFilterOptions {
+            min_chars: args.min_chars,
+            max_chars: args.max_chars,
+            min_words: args.min_words,
+            max_words: args.max_words,
+            snippet_lines: args.snippet_lines,
+        },
+        dates: DateRange {
+            from: args.from,
+            to: args.to,
+        },
+        stratify_by_year: args.stratify_by_year,
+        task,
+        pii: args.redact_pii.then(|| PiiRules {
+            default: args.pii_categories.iter().copied().collect(),
+            languages: args
+                .pii_language
+                .iter()
+                .map(|(language, categories)| {
+                    (language.clone(), categories.iter().copied().collect())
+                })
+                .collect(),
+        }),
+        script_mismatch_threshold: args.script_mismatch_threshold,
+        output: OutputOptions {
+            layout: args.layout,
+            rows_per_row_group: args.rows_per_row_group,
+        },
+    };
+
+    let report = Sampler::new(options).sample(source.as_ref(), dst)?;
+    Manifest::new(args, report).write(&Manifest::path_for(dst))?;
+    Ok(())
+}
 
 fn main() {
     let args = cli::Args::parse();
+    logging::init(args.log_level, args.log_format);
 
-    let res = sampler::sample(&args.src, &args.dst);
+    let res = run(&args);
 
     match res {
         Ok(_) => (),
-        Err(e) => eprintln!("Error: {}", e),
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
     }
 }
@@ -0,0 +1,106 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use madlad_sampler::{errors::MadError, sampler::SampleReport};
+
+use crate::cli::Args;
+
+/// Sidecar describing how a sample was produced, written next to the parquet file
+#[derive(Serialize)]
+pub struct Manifest<'a> {
+    pub tool: &'static str,
+    pub tool_version: &'static str,
+    pub args: &'a Args,
+    pub seed: Option<u64>,
+    #[serde(flatten)]
+    pub report: SampleReport,
+}
+
+impl<'a> Manifest<'a> {
+    pub fn new(args: &'a Args, report: SampleReport) -> Self {
+        Manifest {
+            tool: env!("CARGO_PKG_NAME"),
+            tool_version: env!("CARGO_PKG_VERSION"),
+            args,
+            seed: args.seed,
+            report,
+        }
+    }
+
+    /// Path of the manifest for a given destination, `sample.parquet` -> `sample.manifest.json`.
+    /// Other names are kept whole, so that an `out.v2` folder gets `out.v2.manifest.json`
+    pub fn path_for(dst: &Path) -> PathBuf {
+        let name = match dst
+            .extension()
+            .is_some_and(|extension| extension == "parquet")
+        {
+            true => dst.file_stem(),
+            false => dst.file_name(),
+        };
+        let mut name = name.unwrap_or_default().to_os_string();
+        name.push(".manifest.json");
+        dst.with_file_name(name)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), MadError> {
+        let file = File::create(path)?;
+        Ok(serde_json::to_writer_pretty(file, self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::fixtures::{temp_dir, write_file};
+
+    #[test]
+    fn manifests_are_named_after_the_destination() {
+        for (dst, manifest) in [
+            ("out/sample.parquet", "out/sample.manifest.json"),
+            ("out/sample", "out/sample.manifest.json"),
+            ("out/sample.v2", "out/sample.v2.manifest.json"),
+        ] {
+            assert_eq!(Manifest::path_for(Path::new(dst)), Path::new(manifest));
+        }
+    }
+
+    #[test]
+    fn error_counts_reach_the_manifest() {
+        let dir = temp_dir("manifest");
+        let src = dir.join("src");
+        for (lang, lines) in [
+            ("de", "{\"text\": \"Hallo\"}\nnot json\n{\"text\": \"\"}\n"),
+            (
+                "fr",
+                "{\"text\": \"Bonjour\"}\n{\"text\": 1}\n{\"text\": \"\"}\n",
+            ),
+        ] {
+            write_file(&src.join(lang).join("clean_docs_0.jsonl"), lines);
+        }
+        let dst = dir.join("sample.parquet");
+        let args = Args::parse_from(["madlad-sampler".as_ref(), src.as_os_str(), dst.as_os_str()]);
+        crate::run(&args).unwrap();
+
+        let manifest: serde_json::Value =
+            serde_json::from_reader(File::open(Manifest::path_for(&dst)).unwrap()).unwrap();
+        assert_eq!(
+            manifest["errors"],
+            serde_json::json!({
+                "shard_errors": 0,
+                "io_errors": 0,
+                "decode_errors": 2,
+                "empty_documents": 2,
+            })
+        );
+        assert_eq!(manifest["languages"], serde_json::json!({"de": 1, "fr": 1}));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
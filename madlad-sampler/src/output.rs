@@ -0,0 +1,218 @@
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use clap::ValueEnum;
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{Compression, ZstdLevel},
+    file::properties::WriterProperties,
+};
+use serde::Serialize;
+
+use crate::{
+    errors::MadError,
+    schemas::{Document, document_schema, rows_to_batch, schema_metadata},
+    sources::SourceLanguage,
+};
+
+/// How the sampled documents are laid out on disk
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputLayout {
+    /// A single parquet file holding every language
+    #[default]
+    Single,
+    /// A folder holding one `{language}.parquet` file per language
+    PerLanguage,
+    /// A folder of Hive partitions, `lang={iso code}/{language}.parquet`. The files don't hold
+    /// the `lang` column, which readers take from the partition path
+    Hive,
+}
+
+/// Where and how the sample is written
+#[derive(Debug, Default, Clone)]
+pub struct OutputOptions {
+    pub layout: OutputLayout,
+    /// Maximum number of rows per row group, the parquet default when not set
+    pub rows_per_row_group: Option<usize>,
+}
+
+/// A parquet file written to a hidden temporary file next to its destination, and renamed to it
+/// once its footer is written, so that an interrupted run never leaves a truncated file behind
+struct AtomicWriter {
+    writer: ArrowWriter<File>,
+    tmp: PathBuf,
+    path: PathBuf,
+    /// Indices of the document columns written, all of them when not set
+    projection: Option<Vec<usize>>,
+}
+
+impl AtomicWriter {
+    /// Creates the file, without the `partition_column` if one is given
+    fn create(
+        path: PathBuf,
+        props: WriterProperties,
+        partition_column: Option<&str>,
+    ) -> Result<Self, MadError> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| MadError::Custom(format!("Invalid output file: {}", path.display())))?;
+        let tmp = path.with_file_name(format!(".{}.tmp", name.to_string_lossy()));
+        let mut schema = document_schema();
+        let projection = partition_column.map(|column| {
+            (0..schema.fields().len())
+                .filter(|i| schema.field(*i).name() != column)
+                .collect::<Vec<usize>>()
+        });
+        if let Some(projection) = &projection {
+            schema = Arc::new(schema.project(projection)?);
+        }
+        let writer = ArrowWriter::try_new(File::create(&tmp)?, schema, Some(props))?;
+        Ok(AtomicWriter {
+            writer,
+            tmp,
+            path,
+            projection,
+        })
+    }
+
+    fn write(&mut self, records: &[Document]) -> Result<(), MadError> {
+        let mut batch = rows_to_batch(records);
+        if let Some(projection) = &self.projection {
+            batch = batch.project(projection)?;
+        }
+        Ok(self.writer.write(&batch)?)
+    }
+
+    fn finish(self) -> Result<PathBuf, MadError> {
+        if let Err(e) = self.writer.close() {
+            let _ = fs::remove_file(&self.tmp);
+            return Err(e.into());
+        }
+        fs::rename(&self.tmp, &self.path)?;
+        Ok(self.path)
+    }
+}
+
+/// Writes the language samples following an [`OutputLayout`]
+pub struct SampleWriter {
+    dst: PathBuf,
+    options: OutputOptions,
+    /// The open file of the single file layout
+    single: Option<AtomicWriter>,
+    /// Files written so far
+    outputs: Vec<PathBuf>,
+}
+
+impl SampleWriter {
+    /// `dst` is the parquet file of the single file layout, or the folder of the other layouts
+    pub fn new(dst: &Path, options: OutputOptions) -> Result<Self, MadError> {
+        let mut writer = SampleWriter {
+            dst: dst.to_path_buf(),
+            options,
+            single: None,
+            outputs: vec![],
+        };
+        match writer.options.layout {
+            OutputLayout::Single => {
+                writer.single = Some(AtomicWriter::create(
+                    dst.to_path_buf(),
+                    writer.properties()?,
+                    None,
+                )?)
+            }
+            OutputLayout::PerLanguage | OutputLayout::Hive => fs::create_dir_all(dst)?,
+        }
+        Ok(writer)
+    }
+
+    fn properties(&self) -> Result<WriterProperties, MadError> {
+        let mut props = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::try_new(3)?))
+            .set_key_value_metadata(Some(schema_metadata()));
+        if let Some(rows) = self.options.rows_per_row_group {
+            props = props.set_max_row_group_size(rows);
+        }
+        Ok(props.build())
+    }
+
+    /// Writes the sample of a language. In the per-language layouts its file is complete once
+    /// this returns, so partial results of a run can be inspected.
+    pub fn write_language(
+        &mut self,
+        lang: &SourceLanguage,
+        records: &[Document],
+    ) -> Result<(), MadError> {
+        let (path, partition_column) = match self.options.layout {
+            OutputLayout::Single => {
+                return match self.single.as_mut() {
+                    Some(writer) => writer.write(records),
+                    None => Err("Output file already closed".to_string().into()),
+                };
+            }
+            OutputLayout::PerLanguage => (self.dst.join(format!("{}.parquet", lang.name)), None),
+            OutputLayout::Hive => {
+                let partition = self.dst.join(format!("lang={}", lang.tag));
+                fs::create_dir_all(&partition)?;
+                (
+                    partition.join(format!("{}.parquet", lang.name)),
+                    Some("lang"),
+                )
+            }
+        };
+        let mut writer = AtomicWriter::create(path, self.properties()?, partition_column)?;
+        writer.write(records)?;
+        self.outputs.push(writer.finish()?);
+        Ok(())
+    }
+
+    /// Closes the output and returns the files written
+    pub fn finish(mut self) -> Result<Vec<PathBuf>, MadError> {
+        if let Some(writer) = self.single.take() {
+            self.outputs.push(writer.finish()?);
+        }
+        Ok(self.outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+    use crate::fixtures::{document, temp_dir};
+
+    fn column_names(path: &Path) -> Vec<String> {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap();
+        reader
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .collect()
+    }
+
+    #[test]
+    fn hive_files_have_no_partition_column() {
+        let dst = temp_dir("hive");
+        let lang = SourceLanguage::parse("de", vec![]).unwrap();
+        let options = OutputOptions {
+            layout: OutputLayout::Hive,
+            rows_per_row_group: None,
+        };
+        let mut writer = SampleWriter::new(&dst, options).unwrap();
+        writer
+            .write_language(&lang, &[document("Hallo"), document("Welt")])
+            .unwrap();
+        let outputs = writer.finish().unwrap();
+
+        assert_eq!(outputs, [dst.join("lang=deu").join("de.parquet")]);
+        let columns = column_names(&outputs[0]);
+        assert!(!columns.contains(&"lang".to_string()));
+        assert_eq!(columns.len(), document_schema().fields().len() - 1);
+        fs::remove_dir_all(&dst).unwrap();
+    }
+}
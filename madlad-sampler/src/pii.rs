@@ -0,0 +1,389 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    net::Ipv6Addr,
+    sync::LazyLock,
+};
+
+use clap::ValueEnum;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::sources::SourceLanguage;
+
+/// Kind of personal data that can be redacted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PiiCategory {
+    Email,
+    /// International bank account numbers, validated with their mod-97 checksum
+    Iban,
+    /// Payment card numbers, validated with the Luhn checksum
+    Card,
+    /// IPv4 and IPv6 addresses
+    Ip,
+    /// Phone numbers of 8 to 15 digits, written with a leading `+`, an area code in parentheses
+    /// or separated digit groups
+    Phone,
+}
+
+impl PiiCategory {
+    /// Text replacing a redacted span
+    pub fn placeholder(&self) -> &'static str {
+        match self {
+            PiiCategory::Email => "[EMAIL]",
+            PiiCategory::Iban => "[IBAN]",
+            PiiCategory::Card => "[CARD]",
+            PiiCategory::Ip => "[IP]",
+            PiiCategory::Phone => "[PHONE]",
+        }
+    }
+}
+
+/// Checks the mod-97 checksum of an IBAN without spaces
+fn valid_iban(iban: &str) -> bool {
+    if !(15..=34).contains(&iban.len()) {
+        return false;
+    }
+    let (head, tail) = iban.split_at(4);
+    let mut remainder = 0u32;
+    for c in tail.chars().chain(head.chars()) {
+        let value = match c.to_digit(36) {
+            Some(value) => value,
+            None => return false,
+        };
+        remainder = match value {
+            0..=9 => (remainder * 10 + value) % 97,
+            _ => (remainder * 100 + value) % 97,
+        };
+    }
+    remainder == 1
+}
+
+/// Checks the Luhn checksum of a card number made of digits only
+fn valid_luhn(number: &str) -> bool {
+    let sum: u32 = number
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, digit)| match i % 2 {
+            0 => digit,
+            _ if digit * 2 > 9 => digit * 2 - 9,
+            _ => digit * 2,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Checks an IPv6 address written with its 8 groups, or shortened with `::` but keeping at
+/// least 3 groups, so that `d::f` in `std::fmt` isn't taken for an address
+fn valid_ipv6(candidate: &str) -> bool {
+    let groups = candidate
+        .split(':')
+        .filter(|group| !group.is_empty())
+        .count();
+    (groups == 8 || (candidate.contains("::") && groups >= 3))
+        && candidate.parse::<Ipv6Addr>().is_ok()
+}
+
+/// A detector for one category: candidate spans are found with a regex and then validated
+struct Detector {
+    category: PiiCategory,
+    pattern: Regex,
+    validate: fn(&str) -> bool,
+    /// Only accept candidates that aren't preceded or followed by a letter, a digit, `:` or
+    /// `_`, which the regex crate can't check without lookbehind
+    isolated: bool,
+}
+
+impl Detector {
+    /// Length of the longest valid prefix of a candidate that ends at a word, so that a greedy
+    /// match running into the next word (an IBAN followed by a BIC) still finds its first part
+    fn valid_len(&self, candidate: &str) -> Option<usize> {
+        candidate
+            .match_indices(' ')
+            .map(|(end, _)| end)
+            .chain([candidate.len()])
+            .rev()
+            .find(|end| (self.validate)(&candidate[..*end]))
+    }
+
+    /// Length of the valid part of a candidate found at `start..end` in `text`
+    fn valid_span(&self, text: &str, start: usize, end: usize) -> Option<usize> {
+        let len = self.valid_len(&text[start..end])?;
+        let joined = |c: char| c.is_ascii_alphanumeric() || c == ':' || c == '_';
+        let touches = text[..start].chars().next_back().is_some_and(joined)
+            || text[start + len..].chars().next().is_some_and(joined);
+        match self.isolated && touches {
+            true => None,
+            false => Some(len),
+        }
+    }
+}
+
+/// Large numbers written with thousands separators, e.g. `1.000.000.000` or `12 345 678`
+static GROUPED_NUMBER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[0-9]{1,3}(?:[ .,][0-9]{3})+$").unwrap());
+
+/// How phone numbers are written: with a country code, an area code in parentheses or digit
+/// groups separated by spaces, dots, slashes or dashes. Rules out plain runs of digits and
+/// identifiers like ISBNs with single digit groups
+static PHONE_LAYOUT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:\+[0-9]|\([0-9]{2,5}\)|[0-9]{2,5}(?:[ ./-]{1,3}[0-9]{2,8})+$)").unwrap()
+});
+
+/// Phone number candidates starting with a date, e.g. `2019-03-18` or `18.03.2019 19:39`
+static DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:[0-9]{4}[-./][0-9]{1,2}[-./][0-9]{1,2}|[0-9]{1,2}[-./][0-9]{1,2}[-./][0-9]{4})",
+    )
+    .unwrap()
+});
+
+fn digits(candidate: &str) -> String {
+    candidate.chars().filter(char::is_ascii_digit).collect()
+}
+
+fn detectors() -> Vec<Detector> {
+    let detector = |category, pattern: &str, validate| Detector {
+        category,
+        pattern: Regex::new(pattern).expect("Invalid PII pattern"),
+        validate,
+        isolated: false,
+    };
+    vec![
+        detector(
+            PiiCategory::Email,
+            r"(?i)\b[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}\b",
+            |_| true,
+        ),
+        detector(
+            PiiCategory::Iban,
+            r"\b[A-Z]{2}[0-9]{2}(?: ?[A-Z0-9]){11,30}\b",
+            |candidate| valid_iban(&candidate.replace(' ', "")),
+        ),
+        detector(
+            PiiCategory::Card,
+            r"\b[0-9](?:[ -]?[0-9]){12,18}\b",
+            |candidate| {
+                let digits = digits(candidate);
+                (13..=19).contains(&digits.len()) && valid_luhn(&digits)
+            },
+        ),
+        detector(
+            PiiCategory::Ip,
+            r"\b(?:(?:25[0-5]|2[0-4][0-9]|1?[0-9]?[0-9])\.){3}(?:25[0-5]|2[0-4][0-9]|1?[0-9]?[0-9])\b",
+            |_| true,
+        ),
+        Detector {
+            isolated: true,
+            ..detector(
+                PiiCategory::Ip,
+                r"(?i)(?:[0-9a-f]{1,4}|:)(?::[0-9a-f]{0,4}){2,7}",
+                valid_ipv6,
+            )
+        },
+        detector(
+            PiiCategory::Phone,
+            r"[+(]?\b[0-9][0-9 ()./-]{6,20}[0-9]\b",
+            |candidate| {
+                (8..=15).contains(&digits(candidate).len())
+                    && PHONE_LAYOUT.is_match(candidate)
+                    && !GROUPED_NUMBER.is_match(candidate)
+                    && !DATE.is_match(candidate)
+            },
+        ),
+    ]
+}
+
+/// Categories redacted for every language, and overrides for some languages
+#[derive(Debug, Clone)]
+pub struct PiiRules {
+    pub default: BTreeSet<PiiCategory>,
+    /// Categories by language name (`hi_Latn`) or ISO 639-3 code (`hin`)
+    pub languages: BTreeMap<String, BTreeSet<PiiCategory>>,
+}
+
+impl PiiRules {
+    /// Categories redacted for a language, looked up by name first and then by code
+    pub fn categories(&self, lang: &SourceLanguage) -> &BTreeSet<PiiCategory> {
+        self.languages
+            .get(&lang.name)
+            .or_else(|| self.languages.get(&lang.tag))
+            .unwrap_or(&self.default)
+    }
+}
+
+/// Replaces personal data in document texts by per-category placeholders
+pub struct Redactor {
+    detectors: Vec<Detector>,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Redactor {
+            detectors: detectors(),
+        }
+    }
+}
+
+impl Redactor {
+    /// Redacts the given categories from `text` and returns the number of spans replaced per
+    /// category. Spans found by an earlier detector take precedence over overlapping ones.
+    pub fn redact(
+        &self,
+        text: &str,
+        categories: &BTreeSet<PiiCategory>,
+    ) -> (String, BTreeMap<PiiCategory, usize>) {
+        let mut spans: Vec<(usize, usize, PiiCategory)> = vec![];
+        for detector in &self.detectors {
+            if !categories.contains(&detector.category) {
+                continue;
+            }
+            for found in detector.pattern.find_iter(text) {
+                let overlaps = spans
+                    .iter()
+                    .any(|(start, end, _)| found.start() < *end && *start < found.end());
+                if overlaps {
+                    continue;
+                }
+                if let Some(len) = detector.valid_span(text, found.start(), found.end()) {
+                    spans.push((found.start(), found.start() + len, detector.category));
+                }
+            }
+        }
+        spans.sort();
+
+        let mut counts = BTreeMap::new();
+        let mut redacted = String::with_capacity(text.len());
+        let mut last = 0;
+        for (start, end, category) in spans {
+            redacted.push_str(&text[last..start]);
+            redacted.push_str(category.placeholder());
+            *counts.entry(category).or_default() += 1;
+            last = end;
+        }
+        redacted.push_str(&text[last..]);
+        (redacted, counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redact(text: &str, category: PiiCategory) -> (String, BTreeMap<PiiCategory, usize>) {
+        Redactor::default().redact(text, &BTreeSet::from([category]))
+    }
+
+    #[test]
+    fn iban_checksum() {
+        assert!(valid_iban("DE89370400440532013000"));
+        assert!(valid_iban("GB82WEST12345698765432"));
+        assert!(!valid_iban("DE89370400440532013001"));
+        assert!(!valid_iban("DE8937040044"));
+    }
+
+    #[test]
+    fn luhn_checksum() {
+        assert!(valid_luhn("4111111111111111"));
+        assert!(valid_luhn("5500005555555559"));
+        assert!(!valid_luhn("4111111111111112"));
+    }
+
+    #[test]
+    fn redacts_valid_ibans_only() {
+        let (text, counts) = redact("IBAN: DE89 3704 0044 0532 0130 00.", PiiCategory::Iban);
+        assert_eq!(text, "IBAN: [IBAN].");
+        assert_eq!(counts, BTreeMap::from([(PiiCategory::Iban, 1)]));
+
+        let (text, counts) = redact("IBAN: DE89 3704 0044 0532 0130 01.", PiiCategory::Iban);
+        assert_eq!(text, "IBAN: DE89 3704 0044 0532 0130 01.");
+        assert!(counts.is_empty());
+    }
+
+    #[test]
+    fn redacts_iban_followed_by_words() {
+        let (text, _) = redact(
+            "IBAN DE89370400440532013000 BIC COBADEFFXXX",
+            PiiCategory::Iban,
+        );
+        assert_eq!(text, "IBAN [IBAN] BIC COBADEFFXXX");
+
+        let (text, _) = redact(
+            "IBAN DE89 3704 0044 0532 0130 00 AT COMMERZBANK",
+            PiiCategory::Iban,
+        );
+        assert_eq!(text, "IBAN [IBAN] AT COMMERZBANK");
+    }
+
+    #[test]
+    fn redacts_valid_cards_only() {
+        let (text, _) = redact("Card 4111 1111 1111 1111, exp 12/27", PiiCategory::Card);
+        assert_eq!(text, "Card [CARD], exp 12/27");
+
+        let (text, counts) = redact("Card 4111 1111 1111 1112", PiiCategory::Card);
+        assert_eq!(text, "Card 4111 1111 1111 1112");
+        assert!(counts.is_empty());
+    }
+
+    #[test]
+    fn redacts_phone_layouts() {
+        for phone in [
+            "+49 30 1234567",
+            "(030) 123 4567",
+            "030/1234567",
+            "0151-12345678",
+            "+33 1 23 45 67 89",
+        ] {
+            let (text, _) = redact(&format!("Tel. {phone} ab 9 Uhr"), PiiCategory::Phone);
+            assert_eq!(text, "Tel. [PHONE] ab 9 Uhr", "{phone}");
+        }
+    }
+
+    #[test]
+    fn keeps_numbers_that_are_not_phones() {
+        for text in [
+            "Population 1234567890",
+            "ISBN 978-3-16-148410-0",
+            "Am 2019-03-18 um 19:39",
+            "Am 18.03.2019 19:39",
+            "Budget 1.000.000.000 EUR",
+            "Budget 1,250,000,000 USD",
+            "Population 12 345 678",
+        ] {
+            let (redacted, counts) = redact(text, PiiCategory::Phone);
+            assert_eq!(redacted, text);
+            assert!(counts.is_empty(), "{text}");
+        }
+    }
+
+    #[test]
+    fn redacts_emails_and_ips() {
+        let categories = BTreeSet::from([PiiCategory::Email, PiiCategory::Ip]);
+        let (text, counts) = Redactor::default().redact(
+            "Mail jane.doe@example.org from 192.168.0.1 or 2001:db8::1",
+            &categories,
+        );
+        assert_eq!(text, "Mail [EMAIL] from [IP] or [IP]");
+        assert_eq!(
+            counts,
+            BTreeMap::from([(PiiCategory::Email, 1), (PiiCategory::Ip, 2)])
+        );
+    }
+
+    #[test]
+    fn keeps_code_and_times_that_are_not_ips() {
+        for text in [
+            "std::cout << x;",
+            "use std::fmt::Debug;",
+            "deadbeef::1",
+            "Beginn 12:30:45",
+            "Vec::<u8>::new()",
+        ] {
+            let (redacted, counts) = redact(text, PiiCategory::Ip);
+            assert_eq!(redacted, text);
+            assert!(counts.is_empty(), "{text}");
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use std::time::{Duration, Instant};
+
+use log::info;
+use serde::Serialize;
+
+/// Documents that couldn't be used, counted over the whole run
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ErrorCounts {
+    /// Shards that couldn't be opened
+    pub shard_errors: usize,
+    /// Lines that couldn't be read from a shard
+    pub io_errors: usize,
+    /// Documents that couldn't be decoded
+    pub decode_errors: usize,
+    /// Documents with an empty or whitespace-only text
+    pub empty_documents: usize,
+}
+
+impl ErrorCounts {
+    pub fn add(&mut self, other: &ErrorCounts) {
+        self.shard_errors += other.shard_errors;
+        self.io_errors += other.io_errors;
+        self.decode_errors += other.decode_errors;
+        self.empty_documents += other.empty_documents;
+    }
+}
+
+/// Shortest time between two progress reports within a language
+const REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Formats a duration as `HH:MM:SS`, or `--:--:--` when it is unknown
+fn format_duration(duration: Option<Duration>) -> String {
+    let Some(duration) = duration else {
+        return "--:--:--".to_string();
+    };
+    let seconds = duration.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Items processed per second, 0 before any time elapsed
+fn rate(count: u64, elapsed: Duration) -> f64 {
+    match elapsed.is_zero() {
+        true => 0.0,
+        false => count as f64 / elapsed.as_secs_f64(),
+    }
+}
+
+/// Time left to process `total` items, after `done` of them took `elapsed`. Unknown before
+/// anything was done, or when the total is unknown (0)
+fn eta(elapsed: Duration, done: f64, total: usize) -> Option<Duration> {
+    match done > 0.0 && total > 0 {
+        true => Some(elapsed.mul_f64((total as f64 - done).max(0.0) / done)),
+        false => None,
+    }
+}
+
+/// Tracks how many languages were sampled, and logs the throughput and estimated time left
+/// after each of them, and periodically while a language is sampled
+pub struct Progress {
+    total: usize,
+    done: usize,
+    documents: u64,
+    started: Instant,
+    language_started: Instant,
+    last_report: Instant,
+}
+
+impl Progress {
+    pub fn new(total: usize) -> Self {
+        Progress {
+            total,
+            done: 0,
+            documents: 0,
+            started: Instant::now(),
+            language_started: Instant::now(),
+            last_report: Instant::now(),
+        }
+    }
+
+    pub fn start_language(&mut self) {
+        self.language_started = Instant::now();
+        self.last_report = Instant::now();
+    }
+
+    /// Time left to sample the remaining languages, after `done` of them were sampled
+    fn remaining(&self, done: f64) -> Option<Duration> {
+        eta(self.started.elapsed(), done, self.total)
+    }
+
+    /// Logs the progress within a language after a shard was read, at most every
+    /// `REPORT_INTERVAL`. The ETA assumes the remaining shards of the language are all read,
+    /// although sampling stops once the sample is full.
+    pub fn finish_shard(&mut self, name: &str, shards: usize, total_shards: usize, documents: u64) {
+        if self.last_report.elapsed() < REPORT_INTERVAL || total_shards == 0 {
+            return;
+        }
+        self.last_report = Instant::now();
+        let elapsed = self.language_started.elapsed();
+        let done = self.done as f64 + shards as f64 / total_shards as f64;
+        info!(
+            "[{}/{}] {}: read {} of {} shards, {} documents ({:.0} docs/s), ETA {}",
+            self.done + 1,
+            self.total,
+            name,
+            shards,
+            total_shards,
+            documents,
+            rate(documents, elapsed),
+            format_duration(self.remaining(done)),
+        );
+    }
+
+    /// Logs the progress after a language was sampled, having read `documents` documents
+    pub fn finish_language(&mut self, name: &str, documents: u64) {
+        self.done += 1;
+        self.documents += documents;
+        let elapsed = self.language_started.elapsed();
+        let total_elapsed = self.started.elapsed();
+        let remaining = self.remaining(self.done as f64);
+        info!(
+            "[{}/{}] {}: read {} documents in {:.1}s ({:.0} docs/s), {:.0} docs/s overall, ETA {}",
+            self.done,
+            self.total,
+            name,
+            documents,
+            elapsed.as_secs_f64(),
+            rate(documents, elapsed),
+            rate(self.documents, total_elapsed),
+            format_duration(remaining),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_counts_add_up() {
+        let mut total = ErrorCounts::default();
+        for counts in [
+            ErrorCounts {
+                shard_errors: 1,
+                io_errors: 2,
+                decode_errors: 3,
+                empty_documents: 4,
+            },
+            ErrorCounts {
+                shard_errors: 10,
+                io_errors: 20,
+                decode_errors: 30,
+                empty_documents: 40,
+            },
+        ] {
+            total.add(&counts);
+        }
+        assert_eq!(
+            (
+                total.shard_errors,
+                total.io_errors,
+                total.decode_errors,
+                total.empty_documents
+            ),
+            (11, 22, 33, 44)
+        );
+    }
+
+    #[test]
+    fn rate_without_elapsed_time() {
+        assert_eq!(rate(1000, Duration::ZERO), 0.0);
+        assert_eq!(rate(0, Duration::ZERO), 0.0);
+        assert_eq!(rate(1000, Duration::from_secs(4)), 250.0);
+    }
+
+    #[test]
+    fn eta_extrapolates_the_elapsed_time() {
+        let elapsed = Duration::from_secs(60);
+        assert_eq!(eta(elapsed, 1.0, 4), Some(Duration::from_secs(180)));
+        assert_eq!(eta(elapsed, 2.5, 5), Some(Duration::from_secs(60)));
+        assert_eq!(eta(elapsed, 4.0, 4), Some(Duration::ZERO));
+        // More done than expected never gives a negative ETA
+        assert_eq!(eta(elapsed, 5.0, 4), Some(Duration::ZERO));
+        assert_eq!(eta(Duration::ZERO, 1.0, 4), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn eta_is_unknown_before_progress_or_without_total() {
+        assert_eq!(eta(Duration::from_secs(60), 0.0, 4), None);
+        assert_eq!(eta(Duration::from_secs(60), 1.0, 0), None);
+        assert_eq!(format_duration(None), "--:--:--");
+        assert_eq!(
+            format_duration(Some(Duration::from_secs(3 * 3600 + 25 * 60 + 7))),
+            "03:25:07"
+        );
+    }
+}
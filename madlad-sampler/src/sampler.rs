@@ -1,247 +1,602 @@
-use core::str::FromStr;
 use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    path::PathBuf,
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
 };
 
-use arrow::array::RecordBatch;
-use flate2::read::GzDecoder;
-use isolang::Language;
-use parquet::{
-    arrow::ArrowWriter,
-    basic::{Compression, ZstdLevel},
-    file::properties::WriterProperties,
-};
-use walkdir::{DirEntry, WalkDir};
+use log::{debug, info, warn};
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+use serde::Serialize;
+use unicode_script::Script;
 
-use madlad_sampler::{
+use crate::{
+    dates::{DateRange, TimestampStats, YearQuota},
+    dedup::{DedupScope, DedupStats, Deduplicator, Fingerprint},
+    domains::{DomainCap, DomainDiversity},
     errors::MadError,
-    schemas::{Document, MadBuilder, MadDocument, rows_to_batch},
+    filters::{TextFilter, TextFilterOptions},
+    output::{OutputOptions, SampleWriter},
+    pii::{PiiCategory, PiiRules, Redactor},
+    progress::{ErrorCounts, Progress},
+    schemas::Document,
+    scripts::{ScriptStats, dominant_script, expected_scripts, mismatch_share, script_counts},
+    sources::{CorpusSource, Shard, SourceLanguage},
+    tasks::{TaskCoverage, TaskLanguages},
 };
 
-fn process_jsonline(
-    line: String,
-    lang: String,
-    script: Option<String>,
-    locale: Option<String>,
-    clean: bool,
-    version: String,
-) -> Result<Document, MadError> {
-    let mad_doc: MadDocument = serde_json::from_str(&line)?;
-    let doc = Document {
-        text: mad_doc.text,
-        lang,
-        script,
-        locale,
-        timestamp: mad_doc.timestamp,
-        url: mad_doc.url,
-        clean,
-        source: "MADLAD".to_string(),
-        version,
-    };
-    Ok(doc)
+/// Number of clean documents sampled per language. Up to twice as many noisy documents are added.
+pub const SAMPLE_SIZE: usize = 1000;
+
+/// How documents are selected from a corpus
+#[derive(Default)]
+pub struct SamplerOptions {
+    /// Seed used to shuffle the order in which the shards of a language are read.
+    /// Shards are read in the source order when no seed is given
+    pub seed: Option<u64>,
+    /// Maximum number of documents per registered domain in each language sample
+    pub max_per_domain: Option<usize>,
+    pub dedup: DedupScope,
+    pub text: TextFilterOptions,
+    /// Only documents crawled in this range are sampled
+    pub dates: DateRange,
+    /// Split each language sample evenly across the years of `dates`, which must have both bounds
+    pub stratify_by_year: bool,
+    /// Only the languages of the task are sampled, if given
+    pub task: Option<TaskLanguages>,
+    /// Personal data redacted from the sampled documents, if given
+    pub pii: Option<PiiRules>,
+    /// Skip documents with a larger share of characters outside of the declared or default
+    /// script of their language
+    pub script_mismatch_threshold: Option<f64>,
+    pub output: OutputOptions,
 }
 
-fn process_lang(dir: DirEntry) -> Result<Vec<Document>, MadError> {
-    // This is insane, but we can do it because we know these are language codes
-    let language = dir
-        .path()
-        .components()
-        .next_back()
-        .unwrap()
-        .as_os_str()
-        .to_os_string()
-        .into_string()
-        .unwrap();
-    let new_version = match dir.path().to_str().is_some_and(|s| s.contains("data-v1p5")) {
-        true => "v1.5".to_string(),
-        false => "v1".to_string(),
-    };
-    let tag: String;
-    let script: Option<String>;
-    let locale: Option<String>;
-    let lang_parts: Vec<&str> = language.split('_').collect();
-
-    let bcp_tag = lang_parts[0];
-    let iso_tag = match Language::from_str(bcp_tag) {
-        Ok(language) => language.to_639_3(),
-        Err(e) => return Err(e.into()),
-    };
-    match lang_parts.len() {
-        1 => {
-            tag = iso_tag.to_string();
-            script = None;
-            locale = None;
+impl SamplerOptions {
+    /// Per-year quotas of `limit` documents, when stratifying by year
+    fn year_quota(&self, limit: usize) -> Option<YearQuota> {
+        match self.stratify_by_year {
+            true => self
+                .dates
+                .years()
+                .map(|years| YearQuota::new(&years, limit)),
+            false => None,
         }
-        2 => {
-            tag = iso_tag.to_string();
-            script = Some(lang_parts[1].to_string());
-            locale = None;
+    }
+}
+
+/// A shard read by one of the sampled languages
+#[derive(Debug, Serialize)]
+pub struct InputFile {
+    pub path: String,
+    pub size: u64,
+}
+
+/// What a sampling run read and produced
+#[derive(Debug, Default, Serialize)]
+pub struct SampleReport {
+    pub inputs: Vec<InputFile>,
+    /// Parquet files written
+    pub outputs: Vec<String>,
+    /// Number of sampled documents per language
+    pub languages: BTreeMap<String, usize>,
+    /// Domain diversity of each language sample
+    pub domains: BTreeMap<String, DomainDiversity>,
+    /// Duplicates dropped from each language sample, if deduplication was enabled
+    pub dedup: BTreeMap<String, DedupStats>,
+    /// Timestamps of the documents read for each language sample
+    pub timestamps: BTreeMap<String, TimestampStats>,
+    /// Spans of personal data redacted per category from each language sample, if enabled
+    pub pii_redactions: BTreeMap<String, BTreeMap<PiiCategory, usize>>,
+    /// Detected scripts of each language sample
+    pub scripts: BTreeMap<String, ScriptStats>,
+    /// Documents that couldn't be used, over every language
+    pub errors: ErrorCounts,
+    /// Coverage of the annotation task languages, if sampling was restricted to them
+    pub task_coverage: Option<TaskCoverage>,
+}
+
+/// Per-language sampling state shared by every shard of a language
+struct LangSample<'a> {
+    records: Vec<Document>,
+    domains: DomainCap,
+    dedup: &'a mut Deduplicator,
+    timestamps: TimestampStats,
+    /// Per-year quotas of the clean or noisy part of the sample, when stratifying by year
+    years: Option<YearQuota>,
+    /// Redactor and categories redacted from the sampled documents, if redaction is enabled
+    pii: Option<(&'a Redactor, &'a BTreeSet<PiiCategory>)>,
+    pii_counts: BTreeMap<PiiCategory, usize>,
+    /// Documents read from the shards, including the ones that couldn't be used
+    read: u64,
+    errors: ErrorCounts,
+    /// Scripts the language is expected to be written in
+    expected_scripts: Vec<Script>,
+    /// Share of characters in other scripts above which documents are skipped, if set
+    script_threshold: Option<f64>,
+    scripts: ScriptStats,
+}
+
+impl<'a> LangSample<'a> {
+    fn new(max_per_domain: Option<usize>, dedup: &'a mut Deduplicator) -> Self {
+        LangSample {
+            records: vec![],
+            domains: DomainCap::new(max_per_domain),
+            dedup,
+            timestamps: TimestampStats::default(),
+            years: None,
+            pii: None,
+            pii_counts: BTreeMap::new(),
+            read: 0,
+            errors: ErrorCounts::default(),
+            expected_scripts: vec![],
+            script_threshold: None,
+            scripts: ScriptStats::default(),
+        }
+    }
+
+    /// Adds a document to the sample unless its year is full, it was already sampled or its
+    /// domain is full. Personal data is redacted from the documents that enter the sample.
+    fn push(&mut self, mut doc: Document, fingerprint: Option<Fingerprint>) {
+        if self
+            .years
+            .as_ref()
+            .is_some_and(|years| !years.has_room(&doc))
+        {
+            return; // Skip documents from years that already filled their quota
+        }
+        if fingerprint
+            .as_ref()
+            .is_some_and(|fingerprint| self.dedup.is_duplicate(fingerprint))
+        {
+            return; // Skip duplicates, the next documents replace them
         }
-        3 => {
-            tag = iso_tag.to_string();
-            script = Some(lang_parts[1].to_string());
-            locale = Some(lang_parts[2].to_string());
+        if !self.domains.admit(doc.url.as_deref()) {
+            return; // Skip documents from domains that already filled their quota
         }
-        _ => {
-            return Err(format!("Invalid language format: {language}").into());
+        if let Some(fingerprint) = &fingerprint {
+            self.dedup.insert(fingerprint);
         }
+        if let Some(years) = self.years.as_mut() {
+            years.insert(&doc);
+        }
+        if let Some((redactor, categories)) = self.pii {
+            let (text, counts) = redactor.redact(&doc.text, categories);
+            doc.text = text;
+            doc.pii_redactions = Some(counts.values().sum::<usize>() as u32);
+            for (category, count) in counts {
+                *self.pii_counts.entry(category).or_default() += count;
+            }
+        }
+        self.records.push(doc);
     }
-    println!(
-        "Processing language: {}, script: {:?}, locale: {:?}",
-        tag, script, locale
-    );
-
-    let mut records: Vec<Document> = vec![];
-    let sample_size = 1000; // Limit the number of records to sample
-
-    let clean_file_paths = WalkDir::new(dir.path())
-        .min_depth(1)
-        .max_depth(1)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(|e| {
-            e.file_type().is_file() && e.path().to_str().is_some_and(|s| s.contains("clean_"))
-        });
-
-    for clean_file in clean_file_paths {
-        println!("Processing clean file: {}", clean_file.path().display());
-
-        let jsonl = {
-            let file = File::open(clean_file.path()).unwrap();
-            let gzip = GzDecoder::new(file);
-            BufReader::new(gzip)
+}
+
+/// What sampling a language produced
+struct LangResult {
+    records: Vec<Document>,
+    diversity: DomainDiversity,
+    timestamps: TimestampStats,
+    /// Redactions per category, if redaction is enabled
+    pii: Option<BTreeMap<PiiCategory, usize>>,
+    read: u64,
+    errors: ErrorCounts,
+    scripts: ScriptStats,
+}
+
+/// Reads documents from a shard into the sample until it holds `limit` documents.
+fn process_shard(
+    source: &dyn CorpusSource,
+    lang: &SourceLanguage,
+    shard: &Shard,
+    limit: usize,
+    sample: &mut LangSample,
+    filter: &mut TextFilter,
+    dates: &DateRange,
+) {
+    let documents = match source.documents(lang, shard) {
+        Ok(documents) => documents,
+        Err(e) => {
+            warn!("Error opening shard {}: {}", shard.path.display(), e);
+            sample.errors.shard_errors += 1;
+            return;
+        }
+    };
+
+    for doc in documents {
+        if sample.records.len() >= limit {
+            break; // Limit to sample size
+        }
+        sample.read += 1;
+        let mut doc = match doc {
+            Ok(doc) => doc,
+            Err(MadError::Io(e)) => {
+                debug!("Error reading line of {}: {}", shard.path.display(), e);
+                sample.errors.io_errors += 1;
+                continue; // Skip this line if there's an error
+            }
+            Err(e) => {
+                debug!("Error decoding document of {}: {}", shard.path.display(), e);
+                sample.errors.decode_errors += 1;
+                continue; // Skip this line if there's an error
+            }
         };
+        if doc.text.trim().is_empty() {
+            sample.errors.empty_documents += 1;
+            continue; // Skip empty and blank documents
+        }
+        sample.timestamps.count(&doc);
+        if !dates.contains(doc.timestamp) {
+            sample.timestamps.out_of_range += 1;
+            continue; // Skip documents crawled outside of the date range
+        }
+        // Duplicates are found on the whole text, not on the snippet the filter may cut
+        let fingerprint = sample.dedup.fingerprint(&doc);
+        if !filter.apply(&mut doc) {
+            continue; // Skip documents that are too short or too long
+        }
+        let counts = script_counts(&doc.text);
+        doc.detected_script =
+            dominant_script(&counts).map(|script| script.short_name().to_string());
+        if sample
+            .script_threshold
+            .is_some_and(|threshold| mismatch_share(&counts, &sample.expected_scripts) > threshold)
+        {
+            sample.scripts.mismatched += 1;
+            continue; // Skip documents written in another script
+        }
+        sample.push(doc, fingerprint);
+    }
+}
 
-        for line in jsonl.lines() {
-            let line = match line {
-                Ok(l) => l,
-                Err(e) => {
-                    eprintln!("Error reading line: {}", e);
-                    continue; // Skip this line if there's an error
-                }
-            };
-            let doc = match process_jsonline(
-                line,
-                tag.clone(),
-                script.clone(),
-                locale.clone(),
-                true,
-                new_version.clone(),
-            ) {
-                Ok(doc) => doc,
-                Err(e) => {
-                    eprintln!("Error Ecoding document: {}", e);
-                    continue; // Skip this line if there's an error
-                }
-            };
-            if doc.text.is_empty() {
-                continue; // Skip empty documents
+/// Samples documents from any [`CorpusSource`] into a single parquet file
+pub struct Sampler {
+    options: SamplerOptions,
+    rng: Option<StdRng>,
+    filter: TextFilter,
+    dedup: Deduplicator,
+    redactor: Redactor,
+}
+
+impl Sampler {
+    pub fn new(options: SamplerOptions) -> Self {
+        Sampler {
+            rng: options.seed.map(StdRng::seed_from_u64),
+            filter: TextFilter::new(options.text.clone(), options.seed),
+            dedup: Deduplicator::new(options.dedup),
+            redactor: Redactor::default(),
+            options,
+        }
+    }
+
+    /// Samples the clean shards of a language, then noisy shards up to twice as many documents
+    fn process_lang(
+        &mut self,
+        source: &dyn CorpusSource,
+        lang: &SourceLanguage,
+        inputs: &mut Vec<InputFile>,
+        progress: &mut Progress,
+    ) -> Result<LangResult, MadError> {
+        let mut shards = source.shards(lang)?;
+        if let Some(rng) = self.rng.as_mut() {
+            shards.shuffle(rng);
+        }
+        let (clean_shards, noisy_shards): (Vec<Shard>, Vec<Shard>) =
+            shards.into_iter().partition(|shard| shard.clean);
+
+        for shard in clean_shards.iter().chain(noisy_shards.iter()) {
+            inputs.push(InputFile {
+                path: shard.path.display().to_string(),
+                size: shard.size,
+            });
+        }
+
+        // Releases are deduplicated against each other when a language has several of them
+        let versions: BTreeSet<&str> = clean_shards
+            .iter()
+            .chain(noisy_shards.iter())
+            .map(|shard| shard.version.as_str())
+            .collect();
+        self.dedup.start_language(versions.len() > 1);
+        let mut sample = LangSample::new(self.options.max_per_domain, &mut self.dedup);
+        sample.years = self.options.year_quota(SAMPLE_SIZE);
+        sample.pii = self
+            .options
+            .pii
+            .as_ref()
+            .map(|rules| (&self.redactor, rules.categories(lang)));
+        sample.expected_scripts = expected_scripts(lang);
+        sample.script_threshold = self.options.script_mismatch_threshold;
+        let total_shards = clean_shards.len() + noisy_shards.len();
+
+        for (i, shard) in clean_shards.iter().enumerate() {
+            if sample.records.len() >= SAMPLE_SIZE {
+                break;
             }
-            records.push(doc);
-            if records.len() >= sample_size {
-                break; // Limit to sample size
+            debug!("Processing clean file: {}", shard.path.display());
+            process_shard(
+                source,
+                lang,
+                shard,
+                SAMPLE_SIZE,
+                &mut sample,
+                &mut self.filter,
+                &self.options.dates,
+            );
+            progress.finish_shard(&lang.name, i + 1, total_shards, sample.read);
+        }
+
+        let clean_len = sample.records.len();
+        sample.years = self.options.year_quota(clean_len);
+
+        for (i, shard) in noisy_shards.iter().enumerate() {
+            if sample.records.len() >= 2 * clean_len {
+                break;
             }
+            debug!("Processing noisy file: {}", shard.path.display());
+            process_shard(
+                source,
+                lang,
+                shard,
+                2 * clean_len,
+                &mut sample,
+                &mut self.filter,
+                &self.options.dates,
+            );
+            let shards = clean_shards.len() + i + 1;
+            progress.finish_shard(&lang.name, shards, total_shards, sample.read);
+        }
+
+        let mut timestamps = sample.timestamps;
+        timestamps.count_sampled(&sample.records);
+        let mut scripts = sample.scripts;
+        scripts.expected = sample
+            .expected_scripts
+            .iter()
+            .map(|script| script.short_name().to_string())
+            .collect();
+        for script in sample
+            .records
+            .iter()
+            .filter_map(|doc| doc.detected_script.as_ref())
+        {
+            *scripts.detected.entry(script.clone()).or_default() += 1;
         }
+        Ok(LangResult {
+            diversity: sample.domains.diversity(),
+            timestamps,
+            pii: sample.pii.map(|_| sample.pii_counts),
+            records: sample.records,
+            read: sample.read,
+            errors: sample.errors,
+            scripts,
+        })
     }
 
-    let clean_len = records.len();
+    /// Samples every language of `source` into `dst`, a parquet file or a folder depending on
+    /// the output layout
+    pub fn sample(
+        &mut self,
+        source: &dyn CorpusSource,
+        dst: &Path,
+    ) -> Result<SampleReport, MadError> {
+        let languages = source.languages()?;
 
-    let noisy_file_paths = WalkDir::new(dir.path())
-        .min_depth(1)
-        .max_depth(1)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(|e| {
-            e.file_type().is_file() && e.path().to_str().is_some_and(|s| s.contains("noisy_"))
-        });
+        let mut report = SampleReport::default();
+        let mut coverage = TaskCoverage::default();
+        let mut found_codes: BTreeSet<String> = BTreeSet::new();
 
-    for noisy_file in noisy_file_paths {
-        println!("Processing noisy file: {}", noisy_file.path().display());
+        // Create the destination file or folder
+        let mut writer = SampleWriter::new(dst, self.options.output.clone())?;
 
-        let jsonl = {
-            let file = File::open(noisy_file.path()).unwrap();
-            let gzip = GzDecoder::new(file);
-            BufReader::new(gzip)
-        };
+        let mut selected = vec![];
+        for lang in languages {
+            found_codes.insert(lang.tag.clone());
+            if self
+                .options
+                .task
+                .as_ref()
+                .is_some_and(|t| !t.codes.contains(&lang.tag))
+            {
+                debug!("Skipping language not in the task: {}", lang.name);
+                coverage.missing_from_task.push(lang.name.clone());
+                continue;
+            }
+            selected.push(lang);
+        }
+        let mut progress = Progress::new(selected.len());
 
-        for line in jsonl.lines() {
-            let line = match line {
-                Ok(l) => l,
-                Err(e) => {
-                    eprintln!("Error reading line: {}", e);
-                    continue; // Skip this line if there's an error
+        for lang in selected {
+            for path in &lang.paths {
+                debug!("Processing lang folder: {}", path.display());
+            }
+            info!(
+                "Processing language: {}, script: {:?}, locale: {:?}",
+                lang.tag, lang.script, lang.locale
+            );
+            progress.start_language();
+
+            match self.process_lang(source, &lang, &mut report.inputs, &mut progress) {
+                Ok(LangResult {
+                    records,
+                    diversity,
+                    timestamps,
+                    pii,
+                    read,
+                    errors,
+                    scripts,
+                }) => {
+                    progress.finish_language(&lang.name, read);
+                    report.errors.add(&errors);
+                    info!(
+                        "Sampled {} documents from {} domains for {}, top domain {:?} ({:.1}%), {} capped",
+                        records.len(),
+                        diversity.distinct_domains,
+                        lang.name,
+                        diversity.top_domain,
+                        diversity.top_domain_share * 100.0,
+                        diversity.capped_documents,
+                    );
+                    if let Some(stats) = self.dedup.finish_language() {
+                        info!(
+                            "Dropped {} duplicates within {} and {} found in other languages ({:.1}%)",
+                            stats.language_duplicates,
+                            lang.name,
+                            stats.global_duplicates,
+                            stats.duplicate_rate * 100.0,
+                        );
+                        report.dedup.insert(lang.name.clone(), stats);
+                    }
+                    if timestamps.unparseable > 0 {
+                        warn!(
+                            "Found {} unparseable timestamps for {}",
+                            timestamps.unparseable, lang.name
+                        );
+                    }
+                    report.timestamps.insert(lang.name.clone(), timestamps);
+                    if scripts.mismatched > 0 {
+                        info!(
+                            "Skipped {} documents of {} not written in {}",
+                            scripts.mismatched,
+                            lang.name,
+                            scripts.expected.join("/")
+                        );
+                    }
+                    report.scripts.insert(lang.name.clone(), scripts);
+                    if let Some(pii) = pii {
+                        let redactions: usize = pii.values().sum();
+                        info!(
+                            "Redacted {} spans of personal data for {}",
+                            redactions, lang.name
+                        );
+                        report.pii_redactions.insert(lang.name.clone(), pii);
+                    }
+                    report.languages.insert(lang.name.clone(), records.len());
+                    report.domains.insert(lang.name.clone(), diversity);
+                    if records.is_empty() {
+                        warn!("No records found for language: {}", lang.name);
+                        continue;
+                    }
+
+                    writer.write_language(&lang, &records)?;
                 }
-            };
-            let doc = match process_jsonline(
-                line,
-                tag.clone(),
-                script.clone(),
-                locale.clone(),
-                false,
-                new_version.clone(),
-            ) {
-                Ok(doc) => doc,
                 Err(e) => {
-                    eprintln!("Error Ecoding document: {}", e);
-                    continue; // Skip this line if there's an error
+                    warn!("One of the languages couldn't be processed: {}", e);
+                    continue;
                 }
             };
-            if doc.text.is_empty() {
-                continue; // Skip empty documents
-            }
-            records.push(doc);
-            if records.len() >= 2 * clean_len {
-                break; // Limit to sample size
-            }
         }
-    }
+        report.outputs = writer
+            .finish()?
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
 
-    Ok(records)
+        if let Some(task) = &self.options.task {
+            coverage.missing_from_source = task.codes.difference(&found_codes).cloned().collect();
+            info!(
+                "Task languages without {} data ({}): {}",
+                source.name(),
+                coverage.missing_from_source.len(),
+                coverage.missing_from_source.join(", ")
+            );
+            info!(
+                "{} languages missing from the task ({}): {}",
+                source.name(),
+                coverage.missing_from_task.len(),
+                coverage.missing_from_task.join(", ")
+            );
+            report.task_coverage = Some(coverage);
+        }
+
+        let errors = &report.errors;
+        info!(
+            "Skipped {} shards that couldn't be opened, {} unreadable lines, {} undecodable documents and {} empty documents",
+            errors.shard_errors, errors.io_errors, errors.decode_errors, errors.empty_documents
+        );
+        Ok(report)
+    }
 }
 
-pub fn sample(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
-    // Get all the langueg dirs
-    let folder_paths: Vec<DirEntry> = WalkDir::new(src)
-        .min_depth(1)
-        .max_depth(1)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(|e| e.file_type().is_dir())
-        .collect();
-
-    // Create the destination file
-    let dst = File::create(dst).unwrap();
-
-    let props = WriterProperties::builder()
-        .set_compression(Compression::ZSTD(ZstdLevel::try_new(3).unwrap()))
-        .build();
-
-    let mut aux_builder = MadBuilder::default();
-    let aux_records = RecordBatch::from(&aux_builder.finish());
-
-    let mut writer = ArrowWriter::try_new(dst, aux_records.schema(), Some(props)).unwrap();
-
-    //iterate over the lang folders in parallel
-    for lang in folder_paths {
-        println!("Processing lang folder: {}", lang.path().display());
-        match process_lang(lang.clone()) {
-            Ok(records) => {
-                if records.is_empty() {
-                    println!("No records found for language: {}", lang.path().display());
-                    continue;
-                }
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
 
-                let batch = rows_to_batch(&records);
-                writer.write(&batch).expect("Writing batch");
-            }
-            Err(e) => {
-                eprintln!("WARNING! One of the languages couldn't be processed: {}", e);
-                continue;
-            }
-        };
+    use arrow::array::{AsArray, RecordBatch};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+    use crate::{
+        fixtures::{madlad_lines, temp_dir, write_file},
+        sources::madlad::MadladSource,
+    };
+
+    fn read_sample(path: &Path) -> Vec<RecordBatch> {
+        ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn samples_clean_then_up_to_twice_as_many_documents() {
+        let dir = temp_dir("sampler-loop");
+        let src = dir.join("src");
+        write_file(
+            &src.join("de/clean_docs_0.jsonl.gz"),
+            &madlad_lines(["Eins", "", "Zwei", "Drei"]),
+        );
+        write_file(
+            &src.join("de/noisy_docs_0.jsonl.gz"),
+            &madlad_lines(["a", "b", "c", "d", "e"]),
+        );
+        write_file(
+            &src.join("fr/clean_docs_0.jsonl.gz"),
+            &madlad_lines(["Un", " \n\t"]),
+        );
+        let source = MadladSource::new(&src, None, "clean_*", "noisy_*").unwrap();
+        let dst = dir.join("sample.parquet");
+
+        let report = Sampler::new(SamplerOptions::default())
+            .sample(&source, &dst)
+            .unwrap();
+        assert_eq!(
+            report.languages,
+            BTreeMap::from([("de".to_string(), 6), ("fr".to_string(), 1)])
+        );
+        assert_eq!(report.inputs.len(), 3);
+        assert_eq!(report.errors.empty_documents, 2);
+        assert_eq!(report.outputs, [dst.display().to_string()]);
+
+        let batches = read_sample(&dst);
+        let rows: Vec<(String, String, bool)> = batches
+            .iter()
+            .flat_map(|batch| {
+                let text = batch.column_by_name("text").unwrap().as_string::<i32>();
+                let lang = batch.column_by_name("lang").unwrap().as_string::<i32>();
+                let clean = batch.column_by_name("clean").unwrap().as_boolean();
+                (0..batch.num_rows())
+                    .map(|i| {
+                        (
+                            text.value(i).to_string(),
+                            lang.value(i).to_string(),
+                            clean.value(i),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let expected = [
+            ("Eins", "deu", true),
+            ("Zwei", "deu", true),
+            ("Drei", "deu", true),
+            ("a", "deu", false),
+            ("b", "deu", false),
+            ("c", "deu", false),
+            ("Un", "fra", true),
+        ]
+        .map(|(text, lang, clean)| (text.to_string(), lang.to_string(), clean));
+        assert_eq!(rows, expected);
+        fs::remove_dir_all(&dir).unwrap();
     }
-    writer.close().unwrap();
-    Ok(())
 }
@@ -1,11 +1,63 @@
 use std::sync::Arc;
 
 use arrow::{
-    array::{ArrayRef, BooleanBuilder, RecordBatch, StringBuilder, StructArray},
-    datatypes::{DataType, Field},
+    array::{
+        ArrayRef, BooleanBuilder, RecordBatch, StringBuilder, StructArray,
+        TimestampMicrosecondBuilder, UInt32Builder, UInt64Builder,
+    },
+    datatypes::{DataType, Field, Fields, Schema, SchemaRef, TimeUnit},
 };
+use chrono::{DateTime, Utc};
+use parquet::file::metadata::KeyValue;
 use serde::Deserialize;
 
+/// Version of the output schema, bumped whenever a column is added, removed or changes type
+pub const SCHEMA_VERSION: &str = "2.2";
+
+/// Parquet key-value metadata key under which [`SCHEMA_VERSION`] is written
+pub const SCHEMA_VERSION_KEY: &str = "madlad_sampler.schema_version";
+
+/// Time zone of the `timestamp` column
+const TIMEZONE: &str = "UTC";
+
+/// Fields of the output schema, in column order
+pub fn document_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("text", DataType::Utf8, false),
+        Field::new("lang", DataType::Utf8, false),
+        Field::new("script", DataType::Utf8, true),
+        Field::new("locale", DataType::Utf8, true),
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Microsecond, Some(TIMEZONE.into())),
+            true,
+        ),
+        Field::new("url", DataType::Utf8, true),
+        Field::new("clean", DataType::Boolean, false),
+        Field::new("source", DataType::Utf8, false),
+        Field::new("version", DataType::Utf8, false),
+        Field::new("source_file", DataType::Utf8, false),
+        Field::new("source_line", DataType::UInt64, false),
+        Field::new("snippet_start", DataType::UInt64, true),
+        Field::new("snippet_end", DataType::UInt64, true),
+        Field::new("pii_redactions", DataType::UInt32, true),
+        Field::new("detected_script", DataType::Utf8, true),
+    ])
+}
+
+/// Output schema of the sampler, shared by every row written by [`rows_to_batch`]
+pub fn document_schema() -> SchemaRef {
+    Arc::new(Schema::new(document_fields()))
+}
+
+/// Parquet key-value metadata recording the version of the output schema
+pub fn schema_metadata() -> Vec<KeyValue> {
+    vec![KeyValue::new(
+        SCHEMA_VERSION_KEY.to_string(),
+        SCHEMA_VERSION.to_string(),
+    )]
+}
+
 #[derive(Debug, Deserialize)]
 pub struct MadDocument {
     pub text: String,
@@ -18,11 +70,27 @@ pub struct Document {
     pub lang: String,
     pub script: Option<String>,
     pub locale: Option<String>,
-    pub timestamp: Option<String>,
+    /// Crawl time of the document, `None` if missing or unparseable
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Timestamp found in the source when it couldn't be parsed. Not written to the output
+    pub unparsed_timestamp: Option<String>,
     pub url: Option<String>,
     pub clean: bool,
     pub source: String,
     pub version: String,
+    /// Path of the shard the document was read from
+    pub source_file: String,
+    /// 1-based line number of the document inside the decompressed shard, its row number for
+    /// parquet shards, or its WARC record number for WET shards, counting every record of the
+    /// file including the leading `warcinfo` one
+    pub source_line: u64,
+    /// Character offsets of the extracted snippet in the original document text
+    pub snippet_start: Option<u64>,
+    pub snippet_end: Option<u64>,
+    /// Number of spans of personal data replaced by placeholders, `None` if redaction is off
+    pub pii_redactions: Option<u32>,
+    /// ISO 15924 code of the script most characters of the text are written in
+    pub detected_script: Option<String>,
 }
 
 #[derive(Debug, Default)]
@@ -31,11 +99,17 @@ pub struct MadBuilder {
     lang: StringBuilder,
     script: StringBuilder,
     locale: StringBuilder,
-    timestamp: StringBuilder,
+    timestamp: TimestampMicrosecondBuilder,
     url: StringBuilder,
     clean: BooleanBuilder,
     source: StringBuilder,
     version: StringBuilder,
+    source_file: StringBuilder,
+    source_line: UInt64Builder,
+    snippet_start: UInt64Builder,
+    snippet_end: UInt64Builder,
+    pii_redactions: UInt32Builder,
+    detected_script: StringBuilder,
 }
 
 impl MadBuilder {
@@ -44,53 +118,42 @@ impl MadBuilder {
         self.lang.append_value(document.lang.as_str());
         self.script.append_option(document.script.as_ref());
         self.locale.append_option(document.locale.as_ref());
-        self.timestamp.append_option(document.timestamp.as_ref());
+        self.timestamp
+            .append_option(document.timestamp.map(|t| t.timestamp_micros()));
         self.url.append_option(document.url.as_ref());
         self.clean.append_value(document.clean);
         self.source.append_value(document.source.as_str());
         self.version.append_value(document.version.as_str());
+        self.source_file.append_value(document.source_file.as_str());
+        self.source_line.append_value(document.source_line);
+        self.snippet_start.append_option(document.snippet_start);
+        self.snippet_end.append_option(document.snippet_end);
+        self.pii_redactions.append_option(document.pii_redactions);
+        self.detected_script
+            .append_option(document.detected_script.as_ref());
     }
 
     /// Note: returns StructArray to allow nesting within another array if desired
     pub fn finish(&mut self) -> StructArray {
-        let text = Arc::new(self.text.finish()) as ArrayRef;
-        let text_field = Arc::new(Field::new("text", DataType::Utf8, false));
-
-        let lang = Arc::new(self.lang.finish()) as ArrayRef;
-        let lang_field = Arc::new(Field::new(";ang", DataType::Utf8, false));
-
-        let script = Arc::new(self.script.finish()) as ArrayRef;
-        let script_field = Arc::new(Field::new("script", DataType::Utf8, true));
-
-        let locale = Arc::new(self.locale.finish()) as ArrayRef;
-        let locale_field = Arc::new(Field::new("locale", DataType::Utf8, true));
-
-        let timestamp = Arc::new(self.timestamp.finish()) as ArrayRef;
-        let timestamp_field = Arc::new(Field::new("timestamp", DataType::Utf8, true));
-
-        let url = Arc::new(self.url.finish()) as ArrayRef;
-        let url_field = Arc::new(Field::new("url", DataType::Utf8, true));
-
-        let clean = Arc::new(self.clean.finish()) as ArrayRef;
-        let clean_field = Arc::new(Field::new("clean", DataType::Boolean, false));
-
-        let source = Arc::new(self.source.finish()) as ArrayRef;
-        let source_field = Arc::new(Field::new("source", DataType::Utf8, false));
-
-        let version = Arc::new(self.version.finish()) as ArrayRef;
-        let version_field = Arc::new(Field::new("version", DataType::Utf8, false));
-
-        StructArray::from(vec![
-            (text_field, text),
-            (lang_field, lang),
-            (script_field, script),
-            (locale_field, locale),
-            (timestamp_field, timestamp),
-            (url_field, url),
-            (clean_field, clean),
-            (source_field, source),
-            (version_field, version),
-        ])
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.text.finish()),
+            Arc::new(self.lang.finish()),
+            Arc::new(self.script.finish()),
+            Arc::new(self.locale.finish()),
+            Arc::new(self.timestamp.finish().with_timezone(TIMEZONE)),
+            Arc::new(self.url.finish()),
+            Arc::new(self.clean.finish()),
+            Arc::new(self.source.finish()),
+            Arc::new(self.version.finish()),
+            Arc::new(self.source_file.finish()),
+            Arc::new(self.source_line.finish()),
+            Arc::new(self.snippet_start.finish()),
+            Arc::new(self.snippet_end.finish()),
+            Arc::new(self.pii_redactions.finish()),
+            Arc::new(self.detected_script.finish()),
+        ];
+
+        StructArray::new(document_fields(), columns, None)
     }
 }
 
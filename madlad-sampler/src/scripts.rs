@@ -0,0 +1,235 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use unicode_script::{Script, UnicodeScript};
+
+use crate::sources::SourceLanguage;
+
+/// Scripts of the languages that are not written in Latin script by default, by ISO 639-3 code
+const DEFAULT_SCRIPTS: &[(&str, &str)] = &[
+    ("ady", "Cyrl"),
+    ("amh", "Ethi"),
+    ("ara", "Arab"),
+    ("arb", "Arab"),
+    ("arz", "Arab"),
+    ("asm", "Beng"),
+    ("ava", "Cyrl"),
+    ("awa", "Deva"),
+    ("azb", "Arab"),
+    ("bak", "Cyrl"),
+    ("bel", "Cyrl"),
+    ("ben", "Beng"),
+    ("bho", "Deva"),
+    ("bod", "Tibt"),
+    ("bpy", "Beng"),
+    ("bua", "Cyrl"),
+    ("bul", "Cyrl"),
+    ("bxr", "Cyrl"),
+    ("che", "Cyrl"),
+    ("chr", "Cher"),
+    ("chv", "Cyrl"),
+    ("ckb", "Arab"),
+    ("cmn", "Hani"),
+    ("div", "Thaa"),
+    ("doi", "Deva"),
+    ("dzo", "Tibt"),
+    ("ell", "Grek"),
+    ("fas", "Arab"),
+    ("gom", "Deva"),
+    ("guj", "Gujr"),
+    ("heb", "Hebr"),
+    ("hin", "Deva"),
+    ("hne", "Deva"),
+    ("hye", "Armn"),
+    ("iku", "Cans"),
+    ("inh", "Cyrl"),
+    ("jpn", "Jpan"),
+    ("kan", "Knda"),
+    ("kat", "Geor"),
+    ("kaz", "Cyrl"),
+    ("kbd", "Cyrl"),
+    ("khm", "Khmr"),
+    ("kir", "Cyrl"),
+    ("kom", "Cyrl"),
+    ("kor", "Kore"),
+    ("krc", "Cyrl"),
+    ("kum", "Cyrl"),
+    ("lao", "Laoo"),
+    ("lbe", "Cyrl"),
+    ("lez", "Cyrl"),
+    ("mag", "Deva"),
+    ("mai", "Deva"),
+    ("mal", "Mlym"),
+    ("mar", "Deva"),
+    ("mhr", "Cyrl"),
+    ("mkd", "Cyrl"),
+    ("mni", "Beng"),
+    ("mon", "Cyrl"),
+    ("mya", "Mymr"),
+    ("myv", "Cyrl"),
+    ("mzn", "Arab"),
+    ("nep", "Deva"),
+    ("new", "Deva"),
+    ("nog", "Cyrl"),
+    ("npi", "Deva"),
+    ("ori", "Orya"),
+    ("ory", "Orya"),
+    ("oss", "Cyrl"),
+    ("pan", "Guru"),
+    ("pbt", "Arab"),
+    ("pnb", "Arab"),
+    ("pus", "Arab"),
+    ("rus", "Cyrl"),
+    ("sah", "Cyrl"),
+    ("san", "Deva"),
+    ("sat", "Olck"),
+    ("shn", "Mymr"),
+    ("sin", "Sinh"),
+    ("skr", "Arab"),
+    ("snd", "Arab"),
+    ("srp", "Cyrl"),
+    ("tam", "Taml"),
+    ("tat", "Cyrl"),
+    ("tel", "Telu"),
+    ("tgk", "Cyrl"),
+    ("tha", "Thai"),
+    ("tir", "Ethi"),
+    ("tyv", "Cyrl"),
+    ("udm", "Cyrl"),
+    ("uig", "Arab"),
+    ("ukr", "Cyrl"),
+    ("urd", "Arab"),
+    ("wuu", "Hani"),
+    ("xal", "Cyrl"),
+    ("yid", "Hebr"),
+    ("yue", "Hani"),
+    ("zho", "Hani"),
+];
+
+/// Languages written in Latin script by default, by ISO 639-3 code. Languages missing from both
+/// tables have no expected script, so their documents are not checked.
+const LATIN_LANGUAGES: &[&str] = &[
+    "afr", "aym", "aze", "bcl", "bos", "bre", "cat", "ceb", "ces", "cos", "cym", "dan", "deu",
+    "eng", "epo", "est", "eus", "fao", "fil", "fin", "fra", "fry", "gla", "gle", "glg", "grn",
+    "hat", "hau", "haw", "hil", "hrv", "hun", "ibo", "ilo", "ind", "isl", "ita", "jav", "kin",
+    "kmr", "lat", "lav", "lit", "ltz", "lug", "mlg", "mlt", "mri", "msa", "nld", "nno", "nob",
+    "nor", "nya", "oci", "orm", "pam", "pol", "por", "que", "ron", "slk", "slv", "smo", "sna",
+    "som", "sot", "spa", "sqi", "sun", "swa", "swe", "tet", "tgl", "ton", "tsn", "tuk", "tur",
+    "uzb", "vie", "war", "wol", "xho", "yor", "zsm", "zul",
+];
+
+/// Unicode scripts a declared ISO 15924 script is written with. Japanese, Korean and Chinese
+/// codes cover several Unicode scripts.
+fn unicode_scripts(declared: &str) -> Vec<Script> {
+    match declared {
+        "Jpan" => vec![Script::Hiragana, Script::Katakana, Script::Han],
+        "Kore" => vec![Script::Hangul, Script::Han],
+        "Hans" | "Hant" => vec![Script::Han],
+        code => Script::from_short_name(code).into_iter().collect(),
+    }
+}
+
+/// Scripts a language is expected to be written in: its declared script if the language name has
+/// one, or else its default script. Empty when the script of the language is not known.
+pub fn expected_scripts(lang: &SourceLanguage) -> Vec<Script> {
+    let declared = match lang.script.as_deref() {
+        Some(script) => Some(script),
+        None if LATIN_LANGUAGES.contains(&lang.tag.as_str()) => Some("Latn"),
+        None => DEFAULT_SCRIPTS
+            .iter()
+            .find(|(code, _)| *code == lang.tag)
+            .map(|(_, script)| *script),
+    };
+    declared.map(unicode_scripts).unwrap_or_default()
+}
+
+/// Number of characters of each script in a text. Characters shared by scripts, like digits and
+/// punctuation, are not counted.
+pub fn script_counts(text: &str) -> HashMap<Script, usize> {
+    let mut counts = HashMap::new();
+    for c in text.chars() {
+        let script = c.script();
+        if !matches!(script, Script::Common | Script::Inherited | Script::Unknown) {
+            *counts.entry(script).or_default() += 1;
+        }
+    }
+    counts
+}
+
+/// Script with the most characters in a text, as an ISO 15924 code like `Latn`
+pub fn dominant_script(counts: &HashMap<Script, usize>) -> Option<Script> {
+    counts
+        .iter()
+        .max_by(|a, b| {
+            a.1.cmp(b.1)
+                .then_with(|| b.0.short_name().cmp(a.0.short_name()))
+        })
+        .map(|(script, _)| *script)
+}
+
+/// Share of the characters of a text that are not written in any of the `expected` scripts
+pub fn mismatch_share(counts: &HashMap<Script, usize>, expected: &[Script]) -> f64 {
+    let total: usize = counts.values().sum();
+    if total == 0 || expected.is_empty() {
+        return 0.0;
+    }
+    let mismatched: usize = counts
+        .iter()
+        .filter(|(script, _)| !expected.contains(script))
+        .map(|(_, count)| count)
+        .sum();
+    mismatched as f64 / total as f64
+}
+
+/// Detected scripts of the documents read for a language sample
+#[derive(Debug, Default, Serialize)]
+pub struct ScriptStats {
+    /// Scripts the language is expected to be written in
+    pub expected: Vec<String>,
+    /// Number of sampled documents per dominant script
+    pub detected: BTreeMap<String, usize>,
+    /// Documents skipped because too much of their text was in another script
+    pub mismatched: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expected(name: &str) -> Vec<Script> {
+        expected_scripts(&SourceLanguage::parse(name, vec![]).unwrap())
+    }
+
+    #[test]
+    fn expected_scripts_of_languages() {
+        assert_eq!(expected("hi"), [Script::Devanagari]);
+        assert_eq!(expected("hi_Latn"), [Script::Latin]);
+        assert_eq!(expected("en"), [Script::Latin]);
+        assert_eq!(expected("bho"), [Script::Devanagari]);
+        assert_eq!(
+            expected("ja"),
+            [Script::Hiragana, Script::Katakana, Script::Han]
+        );
+        // Tulu is written in Kannada script and is in neither table
+        assert!(expected("tcy").is_empty());
+    }
+
+    #[test]
+    fn mismatch_shares() {
+        let hindi = script_counts("नमस्ते दुनिया hello");
+        let share = mismatch_share(&hindi, &expected("hi"));
+        assert!(share > 0.2 && share < 0.5, "{share}");
+        assert!((mismatch_share(&hindi, &expected("en")) + share - 1.0).abs() < 1e-9);
+        assert_eq!(
+            mismatch_share(&script_counts("123 !?"), &expected("hi")),
+            0.0
+        );
+    }
+
+    #[test]
+    fn unknown_languages_are_not_checked() {
+        let tulu = script_counts("ತುಳು ಭಾಷೆ");
+        assert_eq!(dominant_script(&tulu), Some(Script::Kannada));
+        assert_eq!(mismatch_share(&tulu, &expected("tcy")), 0.0);
+    }
+}
@@ -0,0 +1,301 @@
+use std::{
+    collections::BTreeMap,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use globset::{Glob, GlobMatcher};
+use serde::Serialize;
+
+use crate::{
+    dates::source_timestamp,
+    errors::MadError,
+    schemas::{Document, MadDocument},
+    sources::{CorpusSource, Documents, Shard, SourceLanguage, list_dirs, list_files},
+};
+
+/// MADLAD release to sample from
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum, Serialize)]
+pub enum DataVersion {
+    /// The original release, in the `data` folder
+    #[value(name = "v1")]
+    #[serde(rename = "v1")]
+    V1,
+    /// The v1.5 release, in the `data-v1p5` folder
+    #[value(name = "v1.5")]
+    #[serde(rename = "v1.5")]
+    V1p5,
+    /// Both releases, matching languages across them and dropping documents sampled twice
+    #[value(name = "both")]
+    #[serde(rename = "both")]
+    Both,
+}
+
+/// The MADLAD-400 corpus: one folder per language holding gzipped jsonl shards split into
+/// `clean_*` and `noisy_*` documents
+pub struct MadladSource {
+    src: PathBuf,
+    version: Option<DataVersion>,
+    clean: GlobMatcher,
+    noisy: GlobMatcher,
+}
+
+impl MadladSource {
+    /// `src` holds the language folders of a single release, or is the MADLAD root holding the
+    /// `data` and `data-v1p5` folders when a `version` is given
+    pub fn new(
+        src: &Path,
+        version: Option<DataVersion>,
+        clean_glob: &str,
+        noisy_glob: &str,
+    ) -> Result<Self, MadError> {
+        let compile = |glob: &str| {
+            Glob::new(glob)
+                .map(|g| g.compile_matcher())
+                .map_err(|e| MadError::Custom(format!("Invalid shard glob {glob}: {e}")))
+        };
+        Ok(MadladSource {
+            src: src.to_path_buf(),
+            version,
+            clean: compile(clean_glob)?,
+            noisy: compile(noisy_glob)?,
+        })
+    }
+
+    /// Folders holding the language folders of the selected MADLAD releases, with their
+    /// release. Without a `version`, `src` is a `data-v1p5` folder of the v1.5 release or else
+    /// of the v1 release.
+    fn version_roots(&self) -> Result<Vec<(PathBuf, &'static str)>, MadError> {
+        let releases: &[(&str, &'static str)] = match self.version {
+            None => {
+                let release = match self.src.file_name().is_some_and(|name| name == "data-v1p5") {
+                    true => "v1.5",
+                    false => "v1",
+                };
+                return Ok(vec![(self.src.clone(), release)]);
+            }
+            Some(DataVersion::V1) => &[("data", "v1")],
+            Some(DataVersion::V1p5) => &[("data-v1p5", "v1.5")],
+            Some(DataVersion::Both) => &[("data", "v1"), ("data-v1p5", "v1.5")],
+        };
+        releases
+            .iter()
+            .map(|(name, release)| {
+                let root = self.src.join(name);
+                match root.is_dir() {
+                    true => Ok((root, *release)),
+                    false => {
+                        Err(format!("MADLAD release folder not found: {}", root.display()).into())
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Release of a language folder, from the release folder holding it
+    fn release(&self, dir: &Path) -> Result<&'static str, MadError> {
+        self.version_roots()?
+            .into_iter()
+            .find(|(root, _)| dir.parent() == Some(root.as_path()))
+            .map(|(_, release)| release)
+            .ok_or_else(|| format!("Not a MADLAD language folder: {}", dir.display()).into())
+    }
+}
+
+fn process_jsonline(
+    line: String,
+    lang: &SourceLanguage,
+    shard: &Shard,
+    source_line: u64,
+) -> Result<Document, MadError> {
+    let mad_doc: MadDocument = serde_json::from_str(&line)?;
+    let (timestamp, unparsed_timestamp) = source_timestamp(mad_doc.timestamp);
+    let doc = Document {
+        text: mad_doc.text,
+        lang: lang.tag.clone(),
+        script: lang.script.clone(),
+        locale: lang.locale.clone(),
+        timestamp,
+        unparsed_timestamp,
+        url: mad_doc.url,
+        clean: shard.clean,
+        source: "MADLAD".to_string(),
+        version: shard.version.clone(),
+        source_file: shard.path.display().to_string(),
+        source_line,
+        snippet_start: None,
+        snippet_end: None,
+        pii_redactions: None,
+        detected_script: None,
+    };
+    Ok(doc)
+}
+
+impl CorpusSource for MadladSource {
+    fn name(&self) -> &str {
+        "MADLAD"
+    }
+
+    /// Languages are matched across releases by folder name
+    fn language_names(&self) -> Result<BTreeMap<String, Vec<PathBuf>>, MadError> {
+        let mut folders: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+        for (root, _) in self.version_roots()? {
+            for dir in list_dirs(&root) {
+                let language = dir.file_name().to_string_lossy().into_owned();
+                folders.entry(language).or_default().push(dir.into_path());
+            }
+        }
+        Ok(folders)
+    }
+
+    fn shards(&self, lang: &SourceLanguage) -> Result<Vec<Shard>, MadError> {
+        let mut shards: Vec<Shard> = vec![];
+        for dir in &lang.paths {
+            let release = self.release(dir)?;
+            for file in list_files(dir) {
+                let clean = self.clean.is_match(file.file_name());
+                if clean || self.noisy.is_match(file.file_name()) {
+                    shards.push(Shard::new(file.into_path(), clean, release.to_string()));
+                }
+            }
+        }
+        // Interleave the shards of the different releases
+        shards.sort_by(|a, b| {
+            a.path
+                .file_name()
+                .cmp(&b.path.file_name())
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        Ok(shards)
+    }
+
+    fn documents<'a>(
+        &'a self,
+        lang: &'a SourceLanguage,
+        shard: &'a Shard,
+    ) -> Result<Documents<'a>, MadError> {
+        let jsonl = shard.open()?;
+        Ok(Box::new(jsonl.lines().enumerate().map(
+            move |(number, line)| process_jsonline(line?, lang, shard, number as u64 + 1),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::fixtures::{temp_dir, write_file};
+
+    fn shard_names(source: &MadladSource) -> Vec<(String, bool)> {
+        let languages = source.languages().unwrap();
+        source
+            .shards(&languages[0])
+            .unwrap()
+            .iter()
+            .map(|shard| {
+                let name = shard.path.file_name().unwrap().to_string_lossy();
+                (name.into_owned(), shard.clean)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shards_are_selected_by_glob() {
+        let src = temp_dir("madlad-globs");
+        let dir = src.join("de");
+        fs::create_dir(&dir).unwrap();
+        for name in [
+            "clean_docs_0.jsonl.gz",
+            "noisy_docs_0.jsonl.zst",
+            "docs_clean_1.jsonl",
+            "README.txt",
+        ] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let source = MadladSource::new(&src, None, "clean_*", "noisy_*").unwrap();
+        assert_eq!(
+            shard_names(&source),
+            [
+                ("clean_docs_0.jsonl.gz".to_string(), true),
+                ("noisy_docs_0.jsonl.zst".to_string(), false),
+            ]
+        );
+
+        let source = MadladSource::new(&src, None, "*_clean_*.jsonl", "noisy_*.gz").unwrap();
+        assert_eq!(
+            shard_names(&source),
+            [("docs_clean_1.jsonl".to_string(), true)]
+        );
+
+        assert!(MadladSource::new(&src, None, "clean_[", "noisy_*").is_err());
+        fs::remove_dir_all(&src).unwrap();
+    }
+
+    #[test]
+    fn documents_of_both_releases() {
+        let src = temp_dir("madlad-documents");
+        write_file(
+            &src.join("data/hi_Latn/clean_docs_0.jsonl.gz"),
+            concat!(
+                "{\"text\": \"Namaste\", \"timestamp\": \"2019-03-18T19:39:53Z\", \"url\": \"https://a.in/\"}\n",
+                "{\"text\": \"Dhanyavaad\", \"timestamp\": \"yesterday\"}\n",
+            ),
+        );
+        write_file(
+            &src.join("data-v1p5/hi_Latn/noisy_docs_0.jsonl"),
+            "{\"text\": \"Shukriya\"}\n",
+        );
+        let source =
+            MadladSource::new(&src, Some(DataVersion::Both), "clean_*", "noisy_*").unwrap();
+        let languages = source.languages().unwrap();
+        assert_eq!(languages.len(), 1);
+        let lang = &languages[0];
+        assert_eq!(lang.paths.len(), 2);
+
+        let shards = source.shards(lang).unwrap();
+        let documents: Vec<Document> = shards
+            .iter()
+            .flat_map(|shard| source.documents(lang, shard).unwrap())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(documents.len(), 3);
+
+        let first = &documents[0];
+        assert_eq!(first.text, "Namaste");
+        assert_eq!(
+            (
+                first.lang.as_str(),
+                first.script.as_deref(),
+                first.locale.as_deref()
+            ),
+            ("hin", Some("Latn"), None)
+        );
+        assert_eq!(
+            first.timestamp.map(|t| t.to_rfc3339()).as_deref(),
+            Some("2019-03-18T19:39:53+00:00")
+        );
+        assert_eq!(first.url.as_deref(), Some("https://a.in/"));
+        assert!(first.clean);
+        assert_eq!(
+            (first.source.as_str(), first.version.as_str()),
+            ("MADLAD", "v1")
+        );
+        assert_eq!(first.source_file, shards[0].path.display().to_string());
+        assert_eq!(first.source_line, 1);
+
+        let second = &documents[1];
+        assert_eq!(second.timestamp, None);
+        assert_eq!(second.unparsed_timestamp.as_deref(), Some("yesterday"));
+        assert_eq!(second.source_line, 2);
+
+        let noisy = &documents[2];
+        assert!(!noisy.clean);
+        assert_eq!(noisy.version, "v1.5");
+        assert_eq!(noisy.source_line, 1);
+        fs::remove_dir_all(&src).unwrap();
+    }
+}
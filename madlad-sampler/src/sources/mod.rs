@@ -0,0 +1,183 @@
+//! Corpora the sampler can read documents from.
+//!
+//! A [`CorpusSource`] lists the languages of a corpus, the shards holding the documents of each
+//! language, and streams the documents of a shard as [`Document`]s, so that every corpus is
+//! sampled by the same [`crate::sampler::Sampler`] and written with the same schema.
+
+use core::str::FromStr;
+use std::{
+    collections::BTreeMap,
+    io::BufRead,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use isolang::Language;
+use log::warn;
+use walkdir::{DirEntry, WalkDir};
+
+use crate::{compression::open_counted, errors::MadError, schemas::Document};
+
+pub mod madlad;
+pub mod oscar;
+pub mod wet;
+
+/// A language of a corpus
+#[derive(Debug, Clone)]
+pub struct SourceLanguage {
+    /// Name of the language in the corpus, e.g. the MADLAD folder name `hi_Latn`
+    pub name: String,
+    /// ISO 639-3 code
+    pub tag: String,
+    pub script: Option<String>,
+    pub locale: Option<String>,
+    /// Folders or files holding the documents of the language
+    pub paths: Vec<PathBuf>,
+}
+
+impl SourceLanguage {
+    /// Parses a language name made of a ISO 639-1 or 639-3 code, optionally followed by a
+    /// script and a locale, e.g. `de`, `hi_Latn` or `ar_Arab_EG`
+    pub fn parse(language: &str, paths: Vec<PathBuf>) -> Result<Self, MadError> {
+        let tag: String;
+        let script: Option<String>;
+        let locale: Option<String>;
+        let lang_parts: Vec<&str> = language.split('_').collect();
+
+        let bcp_tag = lang_parts[0];
+        let iso_tag = match Language::from_str(bcp_tag) {
+            Ok(language) => language.to_639_3(),
+            Err(e) => return Err(e.into()),
+        };
+        match lang_parts.len() {
+            1 => {
+                tag = iso_tag.to_string();
+                script = None;
+                locale = None;
+            }
+            2 => {
+                tag = iso_tag.to_string();
+                script = Some(lang_parts[1].to_string());
+                locale = None;
+            }
+            3 => {
+                tag = iso_tag.to_string();
+                script = Some(lang_parts[1].to_string());
+                locale = Some(lang_parts[2].to_string());
+            }
+            _ => {
+                return Err(format!("Invalid language format: {language}").into());
+            }
+        }
+        Ok(SourceLanguage {
+            name: language.to_string(),
+            tag,
+            script,
+            locale,
+            paths,
+        })
+    }
+}
+
+/// A file holding documents of a language
+#[derive(Debug, Clone)]
+pub struct Shard {
+    pub path: PathBuf,
+    /// Compressed size in bytes
+    pub size: u64,
+    /// Whether the shard holds clean documents, as opposed to noisy ones
+    pub clean: bool,
+    /// Release of the corpus the shard belongs to
+    pub version: String,
+    /// Compressed bytes read through [`Shard::open`] so far
+    read: Arc<AtomicU64>,
+}
+
+impl Shard {
+    pub fn new(path: PathBuf, clean: bool, version: String) -> Self {
+        let size = path.metadata().map(|m| m.len()).unwrap_or(0);
+        Shard {
+            path,
+            size,
+            clean,
+            version,
+            read: Arc::default(),
+        }
+    }
+
+    /// Opens the shard, decompressing it and counting the compressed bytes read
+    pub fn open(&self) -> Result<Box<dyn BufRead>, MadError> {
+        open_counted(&self.path, self.read.clone())
+    }
+
+    /// Compressed bytes read from the shard through [`Shard::open`]. Stays at 0 for sources
+    /// reading their shards otherwise, like the OSCAR parquet files
+    pub fn read_bytes(&self) -> u64 {
+        self.read.load(Ordering::Relaxed)
+    }
+}
+
+/// Documents streamed from a shard, with per-document errors
+pub type Documents<'a> = Box<dyn Iterator<Item = Result<Document, MadError>> + 'a>;
+
+/// A corpus that can be sampled
+pub trait CorpusSource {
+    /// Name of the corpus, written to the `source` column
+    fn name(&self) -> &str;
+
+    /// Lists the language names found in the corpus with the folders or files holding their
+    /// documents, sorted by name
+    fn language_names(&self) -> Result<BTreeMap<String, Vec<PathBuf>>, MadError>;
+
+    /// Lists the languages of the corpus, sorted by name. Names that can't be parsed are skipped
+    /// with a warning
+    fn languages(&self) -> Result<Vec<SourceLanguage>, MadError> {
+        let mut languages = vec![];
+        for (language, paths) in self.language_names()? {
+            match SourceLanguage::parse(&language, paths) {
+                Ok(lang) => languages.push(lang),
+                Err(e) => {
+                    warn!("One of the languages couldn't be processed: {}", e);
+                }
+            }
+        }
+        Ok(languages)
+    }
+
+    /// Lists the shards of a language, in a stable order
+    fn shards(&self, lang: &SourceLanguage) -> Result<Vec<Shard>, MadError>;
+
+    /// Streams the documents of a shard of a language
+    fn documents<'a>(
+        &'a self,
+        lang: &'a SourceLanguage,
+        shard: &'a Shard,
+    ) -> Result<Documents<'a>, MadError>;
+}
+
+/// Sub-folders of a folder, sorted by name
+pub(crate) fn list_dirs(root: &Path) -> Vec<DirEntry> {
+    WalkDir::new(root)
+        .min_depth(1)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_dir())
+        .collect()
+}
+
+/// Files of a folder, not recursing into sub-folders, sorted by name
+pub(crate) fn list_files(dir: &Path) -> Vec<DirEntry> {
+    WalkDir::new(dir)
+        .min_depth(1)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .collect()
+}
@@ -0,0 +1,237 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use arrow::array::{Array, AsArray, RecordBatch};
+use parquet::arrow::{ProjectionMask, arrow_reader::ParquetRecordBatchReaderBuilder};
+
+use crate::{
+    dates::source_timestamp,
+    errors::MadError,
+    schemas::Document,
+    sources::{CorpusSource, Documents, Shard, SourceLanguage, list_dirs, list_files},
+};
+
+/// Columns read from the OSCAR parquet files
+const COLUMNS: [&str; 4] = [
+    "content",
+    "warc_target_uri",
+    "warc_date",
+    "quality_warnings",
+];
+
+/// OSCAR converted to parquet by `oscar2parquet`: one folder per language holding
+/// `{lang}_part_{n}.parquet` files. OSCAR has no clean and noisy files, so every shard is clean
+/// and documents with quality warnings are skipped.
+pub struct OscarSource {
+    src: PathBuf,
+    version: String,
+}
+
+impl OscarSource {
+    pub fn new(src: &Path, version: &str) -> Self {
+        OscarSource {
+            src: src.to_path_buf(),
+            version: version.to_string(),
+        }
+    }
+}
+
+fn batch_to_documents(
+    batch: &RecordBatch,
+    lang: &SourceLanguage,
+    shard: &Shard,
+) -> Result<Vec<Document>, MadError> {
+    let column = |name: &str| {
+        batch.column_by_name(name).ok_or_else(|| {
+            MadError::Custom(format!("Missing column {name} in {}", shard.path.display()))
+        })
+    };
+    let content = column("content")?.as_string::<i32>();
+    let url = column("warc_target_uri")?.as_string::<i32>();
+    let date = column("warc_date")?.as_string::<i32>();
+    let warnings = column("quality_warnings")?.as_list::<i32>();
+
+    let documents = (0..batch.num_rows())
+        .map(|i| {
+            let (timestamp, unparsed_timestamp) =
+                source_timestamp(date.is_valid(i).then(|| date.value(i).to_string()));
+            Document {
+                text: content.value(i).to_string(),
+                lang: lang.tag.clone(),
+                script: lang.script.clone(),
+                locale: lang.locale.clone(),
+                timestamp,
+                unparsed_timestamp,
+                url: url.is_valid(i).then(|| url.value(i).to_string()),
+                clean: warnings.is_null(i) || warnings.value(i).is_empty(),
+                source: "OSCAR".to_string(),
+                version: shard.version.clone(),
+                source_file: shard.path.display().to_string(),
+                source_line: 0,
+                snippet_start: None,
+                snippet_end: None,
+                pii_redactions: None,
+                detected_script: None,
+            }
+        })
+        .collect();
+    Ok(documents)
+}
+
+impl CorpusSource for OscarSource {
+    fn name(&self) -> &str {
+        "OSCAR"
+    }
+
+    fn language_names(&self) -> Result<BTreeMap<String, Vec<PathBuf>>, MadError> {
+        Ok(list_dirs(&self.src)
+            .into_iter()
+            .map(|dir| {
+                let language = dir.file_name().to_string_lossy().into_owned();
+                (language, vec![dir.into_path()])
+            })
+            .collect())
+    }
+
+    fn shards(&self, lang: &SourceLanguage) -> Result<Vec<Shard>, MadError> {
+        Ok(lang
+            .paths
+            .iter()
+            .flat_map(|dir| list_files(dir))
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".parquet"))
+            .map(|e| Shard::new(e.into_path(), true, self.version.clone()))
+            .collect())
+    }
+
+    fn documents<'a>(
+        &'a self,
+        lang: &'a SourceLanguage,
+        shard: &'a Shard,
+    ) -> Result<Documents<'a>, MadError> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&shard.path)?)?;
+        let mask = ProjectionMask::columns(builder.parquet_schema(), COLUMNS);
+        let reader = builder.with_projection(mask).build()?;
+
+        let documents = reader
+            .flat_map(move |batch| {
+                match batch
+                    .map_err(MadError::from)
+                    .and_then(|batch| batch_to_documents(&batch, lang, shard))
+                {
+                    Ok(documents) => documents.into_iter().map(Ok).collect(),
+                    Err(e) => vec![Err(e)],
+                }
+            })
+            .enumerate()
+            .map(|(number, doc)| {
+                doc.map(|mut doc| {
+                    doc.source_line = number as u64 + 1;
+                    doc
+                })
+            })
+            .filter(|doc| doc.as_ref().map_or(true, |doc| doc.clean));
+        Ok(Box::new(documents))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc};
+
+    use arrow::array::{ArrayRef, ListBuilder, StringArray, StringBuilder};
+    use parquet::arrow::ArrowWriter;
+
+    use super::*;
+    use crate::fixtures::temp_dir;
+
+    /// Content, URL, date and quality warnings of an OSCAR document
+    type Row<'a> = (&'a str, Option<&'a str>, Option<&'a str>, &'a [&'a str]);
+
+    /// Writes an OSCAR parquet file of documents
+    fn write_oscar(path: &Path, rows: &[Row]) {
+        let strings = |values: Vec<Option<&str>>| Arc::new(StringArray::from(values)) as ArrayRef;
+        let mut warnings = ListBuilder::new(StringBuilder::new());
+        for (_, _, _, row_warnings) in rows {
+            match row_warnings.is_empty() {
+                true => warnings.append_null(),
+                false => {
+                    row_warnings
+                        .iter()
+                        .for_each(|warning| warnings.values().append_value(warning));
+                    warnings.append(true);
+                }
+            }
+        }
+        let batch = RecordBatch::try_from_iter([
+            ("content", strings(rows.iter().map(|r| Some(r.0)).collect())),
+            (
+                "warc_target_uri",
+                strings(rows.iter().map(|r| r.1).collect()),
+            ),
+            ("warc_date", strings(rows.iter().map(|r| r.2).collect())),
+            ("quality_warnings", Arc::new(warnings.finish()) as ArrayRef),
+        ])
+        .unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut writer =
+            ArrowWriter::try_new(File::create(path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn documents_without_quality_warnings() {
+        let src = temp_dir("oscar-documents");
+        let path = src.join("fr/fr_part_1.parquet");
+        write_oscar(
+            &path,
+            &[
+                (
+                    "Bonjour",
+                    Some("https://a.fr/"),
+                    Some("2021-09-17T08:44:17Z"),
+                    &[],
+                ),
+                ("Salut", None, None, &["tiny"]),
+                ("Merci", None, Some("hier"), &[]),
+            ],
+        );
+        fs::write(src.join("fr/README.md"), "").unwrap();
+        let source = OscarSource::new(&src, "23.01");
+        let languages = source.languages().unwrap();
+        assert_eq!(languages.len(), 1);
+        let lang = &languages[0];
+
+        let shards = source.shards(lang).unwrap();
+        assert_eq!(shards.len(), 1);
+        let documents: Vec<Document> = source
+            .documents(lang, &shards[0])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(documents.len(), 2);
+
+        let first = &documents[0];
+        assert_eq!(first.text, "Bonjour");
+        assert_eq!(first.lang, "fra");
+        assert_eq!(first.url.as_deref(), Some("https://a.fr/"));
+        assert!(first.timestamp.is_some());
+        assert!(first.clean);
+        assert_eq!(
+            (first.source.as_str(), first.version.as_str()),
+            ("OSCAR", "23.01")
+        );
+        assert_eq!(first.source_file, path.display().to_string());
+        assert_eq!(first.source_line, 1);
+
+        // The document with a quality warning is skipped, but still numbered
+        let second = &documents[1];
+        assert_eq!(second.text, "Merci");
+        assert_eq!(second.unparsed_timestamp.as_deref(), Some("hier"));
+        assert_eq!(second.source_line, 3);
+        fs::remove_dir_all(&src).unwrap();
+    }
+}
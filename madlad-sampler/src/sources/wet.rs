@@ -0,0 +1,298 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+use walkdir::WalkDir;
+
+use crate::{
+    compression::open_shard,
+    dates::source_timestamp,
+    errors::MadError,
+    schemas::Document,
+    sources::{CorpusSource, Documents, Shard, SourceLanguage},
+};
+
+/// A `conversion` record of a WET file
+#[derive(Debug, Default)]
+struct WetRecord {
+    record_type: String,
+    target_uri: Option<String>,
+    date: Option<String>,
+    /// `WARC-Identified-Content-Language`, comma separated ISO 639-3 codes, most likely first
+    languages: Option<String>,
+    content: String,
+}
+
+impl WetRecord {
+    fn language(&self) -> Option<&str> {
+        self.languages.as_deref()?.split(',').next()
+    }
+}
+
+/// Reads the records of a WET file
+struct WetReader<R: BufRead> {
+    reader: R,
+}
+
+impl<R: BufRead> WetReader<R> {
+    fn read_record(&mut self) -> Result<Option<WetRecord>, MadError> {
+        let mut line = String::new();
+        // Skip the blank lines separating records
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            if !line.trim().is_empty() {
+                break;
+            }
+        }
+        if !line.starts_with("WARC/") {
+            return Err(format!("Invalid WARC record header: {}", line.trim()).into());
+        }
+
+        let mut record = WetRecord::default();
+        let mut length: Option<usize> = None;
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim().to_string();
+            match name.trim().to_lowercase().as_str() {
+                "warc-type" => record.record_type = value,
+                "warc-target-uri" => record.target_uri = Some(value),
+                "warc-date" => record.date = Some(value),
+                "warc-identified-content-language" => record.languages = Some(value),
+                "content-length" => length = value.parse().ok(),
+                _ => (),
+            }
+        }
+
+        let length = length
+            .ok_or_else(|| MadError::Custom("WARC record without Content-Length".to_string()))?;
+        let mut content = vec![0; length];
+        self.reader.read_exact(&mut content)?;
+        record.content = String::from_utf8_lossy(&content).into_owned();
+        Ok(Some(record))
+    }
+}
+
+impl<R: BufRead> Iterator for WetReader<R> {
+    type Item = Result<WetRecord, MadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Conversion records of a WET file with their 1-based record number, counting every record
+/// of the file. Other records are skipped
+fn conversions(reader: impl BufRead) -> impl Iterator<Item = (u64, Result<WetRecord, MadError>)> {
+    WetReader { reader }
+        .enumerate()
+        .map(|(number, record)| (number as u64 + 1, record))
+        .filter(|(_, record)| {
+            record
+                .as_ref()
+                .map_or(true, |r| r.record_type == "conversion")
+        })
+}
+
+/// Common Crawl WET files. Languages are taken from the first code of the
+/// `WARC-Identified-Content-Language` header; records without it are skipped.
+///
+/// WET files mix languages, so listing the languages reads every file once and sampling reads
+/// every file again for each language. This is meant for a handful of WET files.
+pub struct WetSource {
+    src: PathBuf,
+    version: String,
+}
+
+impl WetSource {
+    pub fn new(src: &Path, version: &str) -> Self {
+        WetSource {
+            src: src.to_path_buf(),
+            version: version.to_string(),
+        }
+    }
+}
+
+impl CorpusSource for WetSource {
+    fn name(&self) -> &str {
+        "CC-WET"
+    }
+
+    fn language_names(&self) -> Result<BTreeMap<String, Vec<PathBuf>>, MadError> {
+        let files: Vec<PathBuf> = WalkDir::new(&self.src)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file() && e.file_name().to_string_lossy().contains(".wet"))
+            .map(|e| e.into_path())
+            .collect();
+
+        let mut found: BTreeMap<String, BTreeSet<PathBuf>> = BTreeMap::new();
+        for file in files {
+            let records = match open_shard(&file) {
+                Ok(reader) => conversions(reader),
+                Err(e) => {
+                    warn!("Skipping unreadable WET file {}: {}", file.display(), e);
+                    continue;
+                }
+            };
+            for (_, record) in records {
+                match record {
+                    Ok(record) => {
+                        if let Some(language) = record.language() {
+                            found
+                                .entry(language.to_string())
+                                .or_default()
+                                .insert(file.clone());
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Error reading record of {}: {}", file.display(), e);
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(found
+            .into_iter()
+            .map(|(language, paths)| (language, paths.into_iter().collect()))
+            .collect())
+    }
+
+    fn shards(&self, lang: &SourceLanguage) -> Result<Vec<Shard>, MadError> {
+        Ok(lang
+            .paths
+            .iter()
+            .map(|path| Shard::new(path.clone(), true, self.version.clone()))
+            .collect())
+    }
+
+    fn documents<'a>(
+        &'a self,
+        lang: &'a SourceLanguage,
+        shard: &'a Shard,
+    ) -> Result<Documents<'a>, MadError> {
+        let documents = conversions(shard.open()?).filter_map(move |(number, record)| {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => return Some(Err(e)),
+            };
+            if record.language() != Some(lang.name.as_str()) {
+                return None;
+            }
+            let (timestamp, unparsed_timestamp) = source_timestamp(record.date);
+            Some(Ok(Document {
+                text: record.content,
+                lang: lang.tag.clone(),
+                script: lang.script.clone(),
+                locale: lang.locale.clone(),
+                timestamp,
+                unparsed_timestamp,
+                url: record.target_uri,
+                clean: shard.clean,
+                source: "CC-WET".to_string(),
+                version: shard.version.clone(),
+                source_file: shard.path.display().to_string(),
+                source_line: number,
+                snippet_start: None,
+                snippet_end: None,
+                pii_redactions: None,
+                detected_script: None,
+            }))
+        });
+        Ok(Box::new(documents))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::fixtures::{temp_dir, write_file};
+
+    /// A WARC record of the given type with extra headers
+    fn record(record_type: &str, headers: &[(&str, &str)], content: &str) -> String {
+        let mut record = format!("WARC/1.0\r\nWARC-Type: {record_type}\r\n");
+        for (name, value) in headers {
+            record += &format!("{name}: {value}\r\n");
+        }
+        record += &format!("Content-Length: {}\r\n\r\n{content}\r\n\r\n", content.len());
+        record
+    }
+
+    fn conversion(uri: &str, languages: &str, content: &str) -> String {
+        record(
+            "conversion",
+            &[
+                ("WARC-Target-URI", uri),
+                ("WARC-Date", "2024-02-20T10:00:00Z"),
+                ("WARC-Identified-Content-Language", languages),
+            ],
+            content,
+        )
+    }
+
+    #[test]
+    fn documents_of_a_language() {
+        let src = temp_dir("wet-documents");
+        let path = src.join("CC-MAIN-0.warc.wet.gz");
+        write_file(
+            &path,
+            &[
+                record("warcinfo", &[], "software: test"),
+                conversion("https://a.de/", "deu,eng", "Hallo\nWelt"),
+                conversion("https://b.fr/", "fra", "Bonjour"),
+                record("metadata", &[], "{}"),
+                conversion("https://c.de/", "deu", "Tschüss"),
+            ]
+            .concat(),
+        );
+        let source = WetSource::new(&src, "CC-MAIN-2024-10");
+        let languages = source.languages().unwrap();
+        assert_eq!(
+            languages
+                .iter()
+                .map(|l| l.name.as_str())
+                .collect::<Vec<_>>(),
+            ["deu", "fra"]
+        );
+        let lang = &languages[0];
+        let shards = source.shards(lang).unwrap();
+        assert_eq!(shards.len(), 1);
+
+        let documents: Vec<Document> = source
+            .documents(lang, &shards[0])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(documents.len(), 2);
+        let first = &documents[0];
+        assert_eq!(first.text, "Hallo\nWelt");
+        assert_eq!(first.lang, "deu");
+        assert_eq!(first.url.as_deref(), Some("https://a.de/"));
+        assert!(first.timestamp.is_some());
+        assert_eq!(
+            (first.source.as_str(), first.version.as_str()),
+            ("CC-WET", "CC-MAIN-2024-10")
+        );
+        assert_eq!(first.source_file, path.display().to_string());
+        // Record numbers count the warcinfo and metadata records
+        assert_eq!(first.source_line, 2);
+        assert_eq!(documents[1].text, "Tschüss");
+        assert_eq!(documents[1].source_line, 5);
+        fs::remove_dir_all(&src).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+use isolang::Language;
+use serde::Serialize;
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// Normalizes an ISO 639-1 or 639-3 code to ISO 639-3, leaving unknown codes untouched.
+pub fn normalize_code(code: &str) -> String {
+    match Language::from_str(code) {
+        Ok(language) => language.to_639_3().to_string(),
+        Err(_) => code.to_string(),
+    }
+}
+
+/// The tags of a Dynabench task config, each with a `display_label` and a `back_label`
+fn task_tags(path: &Path) -> Result<Vec<Yaml>, String> {
+    let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let docs = YamlLoader::load_from_str(&data).map_err(|e| e.to_string())?;
+    let doc = docs
+        .first()
+        .ok_or_else(|| format!("Empty task config: {}", path.display()))?;
+    doc["context"]["generative_context"]["artifacts"]["tags"]
+        .as_vec()
+        .cloned()
+        .ok_or_else(|| format!("No tags found in task config: {}", path.display()))
+}
+
+/// Spellings of a language name to look up in the ISO 639 names: the name itself, without its
+/// parenthetical qualifier (`Swahili (individual language)`) and with an inverted
+/// `Surname, Given` name put back in order (`Sotho, Southern`)
+fn name_variants(name: &str) -> Vec<String> {
+    let name = name.trim().trim_end_matches("[b]").trim();
+    let bare = name.split(" (").next().unwrap_or(name).trim();
+    let mut variants = vec![name.to_string(), bare.to_string()];
+    if let Some((surname, given)) = bare.split_once(", ") {
+        variants.push(format!("{} {}", given, surname));
+    }
+    variants
+}
+
+/// The set of ISO 639-3 codes that can be annotated in the annotation task
+#[derive(Debug, Default)]
+pub struct TaskLanguages {
+    pub codes: BTreeSet<String>,
+}
+
+impl TaskLanguages {
+    /// Fails when some entries of `origin` couldn't be resolved to a language code, rather
+    /// than sampling fewer languages than the task has
+    fn resolved(
+        codes: BTreeSet<String>,
+        unresolved: Vec<String>,
+        origin: &Path,
+    ) -> Result<Self, String> {
+        match unresolved.is_empty() {
+            true => Ok(TaskLanguages { codes }),
+            false => Err(format!(
+                "{} entries of {} couldn't be resolved to a language code: {}",
+                unresolved.len(),
+                origin.display(),
+                unresolved.join(", ")
+            )),
+        }
+    }
+
+    /// Loads the `back_label`s of the tags in a Dynabench task config such as `assets/config.yml`
+    pub fn from_config(path: &Path) -> Result<Self, String> {
+        let mut codes = BTreeSet::new();
+        let mut unresolved = vec![];
+        for tag in task_tags(path)? {
+            match tag["back_label"].as_str() {
+                Some(code) => {
+                    codes.insert(normalize_code(code));
+                }
+                None => unresolved.push(format!("{:?}", tag)),
+            }
+        }
+        Self::resolved(codes, unresolved, path)
+    }
+
+    /// Loads a language list with one entry per line, either a language code or an English
+    /// language name as in `assets/languages.txt`. Names that are a `display_label` of the
+    /// task config, like `assets/config.yml` for `assets/languages.txt`, resolve to their
+    /// `back_label`. Other names resolve to the first alternative separated by `;` that is an
+    /// ISO 639 name.
+    pub fn from_list(path: &Path, config: Option<&Path>) -> Result<Self, String> {
+        let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut labels = HashMap::new();
+        if let Some(config) = config {
+            for tag in task_tags(config)? {
+                if let (Some(label), Some(code)) =
+                    (tag["display_label"].as_str(), tag["back_label"].as_str())
+                {
+                    labels.insert(label.to_string(), normalize_code(code));
+                }
+            }
+        }
+
+        let mut codes = BTreeSet::new();
+        let mut unresolved = vec![];
+        for line in data.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let code = labels.get(line).cloned().or_else(|| {
+                let language = Language::from_str(line).ok().or_else(|| {
+                    line.split(';')
+                        .flat_map(name_variants)
+                        .find_map(|name| Language::from_name(&name))
+                });
+                language.map(|language| language.to_639_3().to_string())
+            });
+            match code {
+                Some(code) => {
+                    codes.insert(code);
+                }
+                None => unresolved.push(line.to_string()),
+            }
+        }
+        Self::resolved(codes, unresolved, path)
+    }
+}
+
+/// How the languages found in the sampled corpus cover the annotation task
+#[derive(Debug, Default, Serialize)]
+pub struct TaskCoverage {
+    /// Task languages without data in the sampled corpus
+    pub missing_from_source: Vec<String>,
+    /// Languages of the sampled corpus that are not part of the task and were skipped
+    pub missing_from_task: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_dir;
+
+    fn asset(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../assets")
+            .join(name)
+    }
+
+    #[test]
+    fn every_task_language_resolves() {
+        let languages =
+            TaskLanguages::from_list(&asset("languages.txt"), Some(&asset("config.yml"))).unwrap();
+        assert!(languages.codes.contains("doi"));
+        assert!(languages.codes.contains("ell"));
+        assert!(languages.codes.contains("mri"));
+        assert!(!languages.codes.contains("dgo"));
+    }
+
+    #[test]
+    fn list_and_config_agree() {
+        let list =
+            TaskLanguages::from_list(&asset("languages.txt"), Some(&asset("config.yml"))).unwrap();
+        let config = TaskLanguages::from_config(&asset("config.yml")).unwrap();
+        assert_eq!(list.codes, config.codes);
+    }
+
+    #[test]
+    fn names_without_a_task_config() {
+        let dir = temp_dir("tasks");
+        let path = dir.join("languages.txt");
+        fs::write(&path, "fr\nSotho, Southern\nDogri\n").unwrap();
+        let languages = TaskLanguages::from_list(&path, None).unwrap();
+        assert_eq!(
+            languages.codes,
+            BTreeSet::from(["dgo", "fra", "sot"].map(str::to_string))
+        );
+        // the task labels take precedence over the ISO 639 names
+        let languages = TaskLanguages::from_list(&path, Some(&asset("config.yml"))).unwrap();
+        assert_eq!(
+            languages.codes,
+            BTreeSet::from(["doi", "fra", "sot"].map(str::to_string))
+        );
+
+        fs::write(&path, "fr\nNot a language\n").unwrap();
+        let err = TaskLanguages::from_list(&path, None).unwrap_err();
+        assert!(err.contains("Not a language"), "{}", err);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
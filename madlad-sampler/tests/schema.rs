@@ -0,0 +1,175 @@
+use arrow::{
+    array::{Array, AsArray},
+    datatypes::{DataType, TimeUnit, TimestampMicrosecondType, UInt64Type},
+};
+use bytes::Bytes;
+use parquet::{
+    arrow::{ArrowWriter, arrow_reader::ParquetRecordBatchReaderBuilder},
+    file::properties::WriterProperties,
+};
+
+use madlad_sampler::{
+    dates::parse_timestamp,
+    schemas::{
+        Document, SCHEMA_VERSION, SCHEMA_VERSION_KEY, document_schema, rows_to_batch,
+        schema_metadata,
+    },
+};
+
+fn full_document() -> Document {
+    Document {
+        text: "Hallo Welt\nZweite Zeile".to_string(),
+        lang: "deu".to_string(),
+        script: Some("Latn".to_string()),
+        locale: Some("AT".to_string()),
+        timestamp: parse_timestamp("2019-03-18T19:39:53Z"),
+        unparsed_timestamp: None,
+        url: Some("https://example.at/welt".to_string()),
+        clean: true,
+        source: "MADLAD".to_string(),
+        version: "v1.5".to_string(),
+        source_file: "data-v1p5/de_Latn_AT/clean_docs_0.jsonl.gz".to_string(),
+        source_line: 42,
+        snippet_start: Some(0),
+        snippet_end: Some(10),
+        pii_redactions: Some(2),
+        detected_script: Some("Latn".to_string()),
+    }
+}
+
+fn minimal_document() -> Document {
+    Document {
+        text: "bonjour".to_string(),
+        lang: "fra".to_string(),
+        script: None,
+        locale: None,
+        timestamp: None,
+        unparsed_timestamp: Some("yesterday".to_string()),
+        url: None,
+        clean: false,
+        source: "MADLAD".to_string(),
+        version: "v1".to_string(),
+        source_file: "data/fr/noisy_docs_3.jsonl.gz".to_string(),
+        source_line: 1,
+        snippet_start: None,
+        snippet_end: None,
+        pii_redactions: None,
+        detected_script: None,
+    }
+}
+
+#[test]
+fn schema_fields() {
+    let expected = [
+        ("text", DataType::Utf8, false),
+        ("lang", DataType::Utf8, false),
+        ("script", DataType::Utf8, true),
+        ("locale", DataType::Utf8, true),
+        (
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            true,
+        ),
+        ("url", DataType::Utf8, true),
+        ("clean", DataType::Boolean, false),
+        ("source", DataType::Utf8, false),
+        ("version", DataType::Utf8, false),
+        ("source_file", DataType::Utf8, false),
+        ("source_line", DataType::UInt64, false),
+        ("snippet_start", DataType::UInt64, true),
+        ("snippet_end", DataType::UInt64, true),
+        ("pii_redactions", DataType::UInt32, true),
+        ("detected_script", DataType::Utf8, true),
+    ];
+
+    let schema = document_schema();
+    assert_eq!(schema.fields().len(), expected.len());
+    for (field, (name, data_type, nullable)) in schema.fields().iter().zip(expected) {
+        assert_eq!(field.name(), name);
+        assert_eq!(field.data_type(), &data_type, "type of {name}");
+        assert_eq!(field.is_nullable(), nullable, "nullability of {name}");
+    }
+}
+
+#[test]
+fn batch_matches_schema() {
+    let batch = rows_to_batch(&[full_document(), minimal_document()]);
+
+    assert_eq!(batch.schema(), document_schema());
+    assert_eq!(batch.num_rows(), 2);
+}
+
+#[test]
+fn empty_batch_matches_schema() {
+    let batch = rows_to_batch(&[]);
+
+    assert_eq!(batch.schema(), document_schema());
+    assert_eq!(batch.num_rows(), 0);
+}
+
+#[test]
+fn optional_columns_are_null() {
+    let batch = rows_to_batch(&[full_document(), minimal_document()]);
+
+    for name in [
+        "script",
+        "locale",
+        "timestamp",
+        "url",
+        "snippet_start",
+        "snippet_end",
+        "pii_redactions",
+        "detected_script",
+    ] {
+        let column = batch.column_by_name(name).unwrap();
+        assert!(column.is_valid(0), "{name} of the full document");
+        assert!(column.is_null(1), "{name} of the minimal document");
+    }
+}
+
+#[test]
+fn values_are_written() {
+    let batch = rows_to_batch(&[full_document()]);
+
+    let lang = batch.column_by_name("lang").unwrap().as_string::<i32>();
+    assert_eq!(lang.value(0), "deu");
+    let source_line = batch
+        .column_by_name("source_line")
+        .unwrap()
+        .as_primitive::<UInt64Type>();
+    assert_eq!(source_line.value(0), 42);
+    let timestamp = batch
+        .column_by_name("timestamp")
+        .unwrap()
+        .as_primitive::<TimestampMicrosecondType>();
+    assert_eq!(timestamp.value(0), 1_552_937_993_000_000);
+    let clean = batch.column_by_name("clean").unwrap().as_boolean();
+    assert!(clean.value(0));
+}
+
+#[test]
+fn parquet_metadata_has_schema_version() {
+    let props = WriterProperties::builder()
+        .set_key_value_metadata(Some(schema_metadata()))
+        .build();
+    let mut buffer = vec![];
+    let mut writer = ArrowWriter::try_new(&mut buffer, document_schema(), Some(props)).unwrap();
+    writer
+        .write(&rows_to_batch(&[full_document(), minimal_document()]))
+        .unwrap();
+    writer.close().unwrap();
+
+    let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(buffer)).unwrap();
+    let version = reader
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .unwrap()
+        .iter()
+        .find(|kv| kv.key == SCHEMA_VERSION_KEY)
+        .and_then(|kv| kv.value.clone());
+    assert_eq!(version.as_deref(), Some(SCHEMA_VERSION));
+
+    let schema = reader.schema();
+    assert_eq!(schema.fields(), document_schema().fields());
+}